@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::fmt;
 
+use super::error::{ParseError, ParseErrorKind};
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Value {
     Two,
@@ -13,12 +15,12 @@ pub enum Value {
     Nine,
     Ten,
     Jack,
-    King,
     Queen,
+    King,
     Ace,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Suit {
     Clubs,
     Diamonds,
@@ -26,7 +28,7 @@ pub enum Suit {
     Spades,
 }
 
-const ALL_VALUES: [Value; 13] = [
+pub const ALL_VALUES: [Value; 13] = [
     Value::Two,
     Value::Three,
     Value::Four,
@@ -37,12 +39,12 @@ const ALL_VALUES: [Value; 13] = [
     Value::Nine,
     Value::Ten,
     Value::Jack,
-    Value::King,
     Value::Queen,
+    Value::King,
     Value::Ace,
 ];
 
-const ALL_SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+pub const ALL_SUITS: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Card {
@@ -62,6 +64,60 @@ impl fmt::Display for Suit {
     }
 }
 
+impl Value {
+    /// Returns the single-character abbreviation of the value, e.g. 'T'
+    /// for Ten or '7' for Seven.
+    pub fn to_char(self) -> char {
+        match self {
+            Value::Two => '2',
+            Value::Three => '3',
+            Value::Four => '4',
+            Value::Five => '5',
+            Value::Six => '6',
+            Value::Seven => '7',
+            Value::Eight => '8',
+            Value::Nine => '9',
+            Value::Ten => 'T',
+            Value::Jack => 'J',
+            Value::Queen => 'Q',
+            Value::King => 'K',
+            Value::Ace => 'A',
+        }
+    }
+
+    /// Returns the plural name of the value, e.g. "Sixes" or "Kings".
+    pub fn plural_name(self) -> &'static str {
+        match self {
+            Value::Two => "Twos",
+            Value::Three => "Threes",
+            Value::Four => "Fours",
+            Value::Five => "Fives",
+            Value::Six => "Sixes",
+            Value::Seven => "Sevens",
+            Value::Eight => "Eights",
+            Value::Nine => "Nines",
+            Value::Ten => "Tens",
+            Value::Jack => "Jacks",
+            Value::Queen => "Queens",
+            Value::King => "Kings",
+            Value::Ace => "Aces",
+        }
+    }
+}
+
+impl Suit {
+    /// Returns the single-character abbreviation of the suit, e.g. 's' for
+    /// Spades.
+    pub fn to_char(self) -> char {
+        match self {
+            Suit::Clubs => 'c',
+            Suit::Diamonds => 'd',
+            Suit::Hearts => 'h',
+            Suit::Spades => 's',
+        }
+    }
+}
+
 impl fmt::Display for Card {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} of {}", self.value, self.suit)
@@ -74,52 +130,48 @@ impl fmt::Debug for Card {
     }
 }
 
-impl std::str::FromStr for Card {
+impl std::str::FromStr for Value {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if !s.is_ascii() {
-            return Err("String must be ASCII");
+        match s {
+            "J" | "j" => Ok(Value::Jack),
+            "Q" | "q" => Ok(Value::Queen),
+            "K" | "k" => Ok(Value::King),
+            "A" | "a" => Ok(Value::Ace),
+            "2" => Ok(Value::Two),
+            "3" => Ok(Value::Three),
+            "4" => Ok(Value::Four),
+            "5" => Ok(Value::Five),
+            "6" => Ok(Value::Six),
+            "7" => Ok(Value::Seven),
+            "8" => Ok(Value::Eight),
+            "9" => Ok(Value::Nine),
+            "10" | "T" | "t" => Ok(Value::Ten),
+            _ => Err("Invalid value"),
         }
+    }
+}
 
-        if s.len() < 2 {
-            return Err("String must contain value and suit");
-        }
-
-        let value_str = &s[0..s.len() - 1];
-
-        let value = match value_str {
-            "J" | "j" => Value::Jack,
-            "Q" | "q" => Value::Queen,
-            "K" | "k" => Value::King,
-            "A" | "a" => Value::Ace,
-            "2" => Value::Two,
-            "3" => Value::Three,
-            "4" => Value::Four,
-            "5" => Value::Five,
-            "6" => Value::Six,
-            "7" => Value::Seven,
-            "8" => Value::Eight,
-            "9" => Value::Nine,
-            "10" => Value::Ten,
-            _ => {
-                return Err("Invalid value");
-            }
-        };
+impl std::str::FromStr for Suit {
+    type Err = &'static str;
 
-        let suit_str = &s[s.len() - 1..s.len()];
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "C" | "c" => Ok(Suit::Clubs),
+            "D" | "d" => Ok(Suit::Diamonds),
+            "H" | "h" => Ok(Suit::Hearts),
+            "S" | "s" => Ok(Suit::Spades),
+            _ => Err("Invalid suit"),
+        }
+    }
+}
 
-        let suit = match suit_str {
-            "C" | "c" => Suit::Clubs,
-            "D" | "d" => Suit::Diamonds,
-            "H" | "h" => Suit::Hearts,
-            "S" | "s" => Suit::Spades,
-            _ => {
-                return Err("Invalid suit");
-            }
-        };
+impl std::str::FromStr for Card {
+    type Err = ParseError;
 
-        Ok(Card::new(value, suit))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Card::parse(s).map_err(|kind| ParseError::new(kind, s))
     }
 }
 
@@ -128,11 +180,38 @@ impl Card {
         Card { value, suit }
     }
 
+    /// Parses a single card, e.g. "qs" or "10h". Unlike `from_str()`, this
+    /// doesn't bother building a descriptive error.
+    pub(crate) fn parse(s: &str) -> Result<Self, ParseErrorKind> {
+        if !s.is_ascii() {
+            return Err(ParseErrorKind::NotAscii);
+        }
+
+        if s.len() < 2 {
+            return Err(ParseErrorKind::MissingSuit);
+        }
+
+        let value = s[0..s.len() - 1]
+            .parse::<Value>()
+            .map_err(|_| ParseErrorKind::InvalidValue)?;
+        let suit = s[s.len() - 1..s.len()]
+            .parse::<Suit>()
+            .map_err(|_| ParseErrorKind::InvalidSuit)?;
+
+        Ok(Card::new(value, suit))
+    }
+
+    /// Returns the compact two-character form of the card, e.g. "Ts" for
+    /// Ten of Spades. This can be parsed back into a Card.
+    pub fn short_name(&self) -> String {
+        format!("{}{}", self.value.to_char(), self.suit.to_char())
+    }
+
     /// Convert a Vector of Cards to a comma-separated string.
     /// Ideally we'd just implement fmt::Display for a Vec<Card> but
     /// this results in a "impl does not reference any types defined in
     /// this crate" error.
-    pub fn vec_to_string(v: &Vec<Card>) -> String {
+    pub fn vec_to_string(v: &[Card]) -> String {
         v.iter()
             .map(|card| format!("{}", card))
             .collect::<Vec<_>>()
@@ -151,12 +230,26 @@ impl Card {
         result
     }
 
-    pub fn try_vec_from<T: AsRef<str>>(value: T) -> Result<Vec<Self>, &'static str> {
-        let mut result = vec![];
-        let s = value.as_ref();
+    pub fn try_vec_from<T: AsRef<str>>(value: T) -> Result<Vec<Self>, ParseError> {
+        Card::try_vec_from_tokens(value.as_ref().split_whitespace())
+    }
 
-        for card_str in s.split_ascii_whitespace() {
-            result.push(card_str.parse::<Card>()?);
+    /// Parses each token into a card, reporting the position of the first
+    /// one that is invalid or repeats an earlier card.
+    pub fn try_vec_from_tokens<I, T>(tokens: I) -> Result<Vec<Self>, ParseError>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut result: Vec<Self> = vec![];
+
+        for (index, token) in tokens.into_iter().enumerate() {
+            let token = token.as_ref();
+            let card = token.parse::<Card>().map_err(|err| err.at_index(index))?;
+            if result.contains(&card) {
+                return Err(ParseError::new(ParseErrorKind::DuplicateCard, token).at_index(index));
+            }
+            result.push(card);
         }
 
         Ok(result)
@@ -186,7 +279,7 @@ impl From<&Value> for u8 {
 
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -201,7 +294,7 @@ impl Ord for Value {
 
 impl PartialOrd for Card {
     fn partial_cmp(&self, other: &Card) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -213,6 +306,61 @@ impl Ord for Card {
     }
 }
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::{Card, Suit, Value};
+
+    fn deserialize_parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        String::deserialize(deserializer)?
+            .parse::<T>()
+            .map_err(D::Error::custom)
+    }
+
+    impl Serialize for Value {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.to_char().to_string())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Value {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize_parsed(deserializer)
+        }
+    }
+
+    impl Serialize for Suit {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.to_char().to_string())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Suit {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize_parsed(deserializer)
+        }
+    }
+
+    impl Serialize for Card {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.short_name())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Card {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize_parsed(deserializer)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Card;
@@ -241,6 +389,41 @@ mod tests {
         assert_eq!("kd".parse::<Card>().unwrap(), Card::new(King, Diamonds));
     }
 
+    #[test]
+    fn test_short_name_round_trips() {
+        for card in Card::new_deck() {
+            assert_eq!(card.short_name().parse::<Card>().unwrap(), card);
+        }
+        assert_eq!(Card::new(Ten, Spades).short_name(), "Ts");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_uses_short_names() {
+        let cards = vec![Card::new(Ten, Spades), Card::new(Ace, Hearts)];
+        let json = serde_json::to_string(&cards).unwrap();
+        assert_eq!(json, r#"["Ts","Ah"]"#);
+        assert_eq!(serde_json::from_str::<Vec<Card>>(&json).unwrap(), cards);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trips_values_and_suits() {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::de::{Deserialize, IntoDeserializer};
+
+        let json = serde_json::to_string(&(Ten, Spades)).unwrap();
+        assert_eq!(json, r#"["T","s"]"#);
+        assert_eq!(
+            serde_json::from_str::<(super::Value, super::Suit)>(&json).unwrap(),
+            (Ten, Spades)
+        );
+        // Formats that aren't self-describing hand back exactly what was
+        // serialized, which is a string.
+        let deserializer: StrDeserializer<Error> = "q".into_deserializer();
+        assert_eq!(super::Value::deserialize(deserializer), Ok(Queen));
+    }
+
     #[test]
     fn try_vec_from_works() {
         assert_eq!(
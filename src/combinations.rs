@@ -0,0 +1,204 @@
+// https://en.wikipedia.org/wiki/Combinatorial_number_system
+use std::ops::Range;
+
+/// Returns the number of ways to choose `k` items from `n`.
+pub fn binomial(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    let mut result: u64 = 1;
+    for i in 0..k {
+        // This division is always exact, since the product of i + 1
+        // consecutive integers is divisible by (i + 1)!.
+        result = result * (n - i) as u64 / (i + 1) as u64;
+    }
+    result
+}
+
+/// Returns the rank of the given combination of strictly increasing indices
+/// in the combinatorial number system, i.e. its position in colexicographic
+/// order.
+pub fn rank(indices: &[usize]) -> u64 {
+    indices
+        .iter()
+        .enumerate()
+        .map(|(i, &index)| binomial(index, i + 1))
+        .sum()
+}
+
+/// Writes the combination of `indices.len()` indices with the given rank
+/// into `indices`. This is the inverse of `rank()`.
+pub fn unrank_into(mut rank: u64, indices: &mut [usize]) {
+    for i in (0..indices.len()).rev() {
+        // Find the largest index whose binomial coefficient fits within what
+        // remains of the rank.
+        let k = i + 1;
+        let mut index = i;
+        while binomial(index + 1, k) <= rank {
+            index += 1;
+        }
+        rank -= binomial(index, k);
+        indices[i] = index;
+    }
+}
+
+/// Returns the combination of `k` indices with the given rank.
+pub fn unrank(rank: u64, k: usize) -> Vec<usize> {
+    let mut indices = vec![0; k];
+    unrank_into(rank, &mut indices);
+    indices
+}
+
+/// Iterates over every way of choosing `k` items from a slice, in
+/// colexicographic order.
+///
+/// The `advance()` method reuses an internal buffer and doesn't allocate;
+/// the `Iterator` implementation allocates a new `Vec` for every combination.
+pub struct Combinations<'a, T: Copy> {
+    items: &'a [T],
+    indices: Vec<usize>,
+    buffer: Vec<T>,
+    next_rank: u64,
+    end_rank: u64,
+}
+
+impl<'a, T: Copy> Combinations<'a, T> {
+    pub fn new(items: &'a [T], k: usize) -> Self {
+        Combinations::with_ranks(items, k, 0..binomial(items.len(), k))
+    }
+
+    /// Iterates over only the combinations whose ranks fall within the given
+    /// range.
+    pub fn with_ranks(items: &'a [T], k: usize, ranks: Range<u64>) -> Self {
+        let end_rank = ranks.end.min(binomial(items.len(), k));
+        let mut indices = vec![0; k];
+        if ranks.start < end_rank {
+            unrank_into(ranks.start, &mut indices);
+        }
+        Combinations {
+            items,
+            indices,
+            buffer: Vec::with_capacity(k),
+            next_rank: ranks.start,
+            end_rank,
+        }
+    }
+
+    /// Iterates over the `shard`th of `num_shards` roughly equal, disjoint
+    /// portions of all the combinations. Iterating over every shard visits
+    /// each combination exactly once.
+    pub fn shard(items: &'a [T], k: usize, shard: usize, num_shards: usize) -> Self {
+        assert!(
+            shard < num_shards,
+            "Shard index must be less than num_shards"
+        );
+        let total = binomial(items.len(), k);
+        let start = total * shard as u64 / num_shards as u64;
+        let end = total * (shard as u64 + 1) / num_shards as u64;
+        Combinations::with_ranks(items, k, start..end)
+    }
+
+    /// Returns the number of combinations left to visit.
+    pub fn remaining(&self) -> u64 {
+        self.end_rank.saturating_sub(self.next_rank)
+    }
+
+    /// Returns the next combination, or `None` if there are no more.
+    pub fn advance(&mut self) -> Option<&[T]> {
+        if self.next_rank >= self.end_rank {
+            return None;
+        }
+
+        self.buffer.clear();
+        for &index in self.indices.iter() {
+            self.buffer.push(self.items[index]);
+        }
+
+        self.next_rank += 1;
+        self.increment_indices();
+
+        Some(&self.buffer)
+    }
+
+    fn increment_indices(&mut self) {
+        let k = self.indices.len();
+        for i in 0..k {
+            let limit = if i + 1 < k {
+                self.indices[i + 1]
+            } else {
+                self.items.len()
+            };
+            if self.indices[i] + 1 < limit {
+                self.indices[i] += 1;
+                for (j, index) in self.indices[0..i].iter_mut().enumerate() {
+                    *index = j;
+                }
+                return;
+            }
+        }
+    }
+}
+
+impl<'a, T: Copy> Iterator for Combinations<'a, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance().map(|combination| combination.to_vec())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining() as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{binomial, rank, unrank, Combinations};
+
+    #[test]
+    fn test_binomial_works() {
+        assert_eq!(binomial(5, 0), 1);
+        assert_eq!(binomial(5, 2), 10);
+        assert_eq!(binomial(2, 5), 0);
+        assert_eq!(binomial(52, 5), 2_598_960);
+        assert_eq!(binomial(48, 5), 1_712_304);
+    }
+
+    #[test]
+    fn test_combinations_visits_everything_in_rank_order() {
+        let items = [1, 2, 3, 4, 5];
+        let all = Combinations::new(&items, 3).collect::<Vec<_>>();
+        assert_eq!(all.len(), 10);
+        assert_eq!(all[0], vec![1, 2, 3]);
+        assert_eq!(all[1], vec![1, 2, 4]);
+        assert_eq!(all[9], vec![3, 4, 5]);
+        for (i, combination) in all.iter().enumerate() {
+            let indices = combination.iter().map(|n| n - 1).collect::<Vec<_>>();
+            assert_eq!(rank(&indices), i as u64);
+            assert_eq!(unrank(i as u64, 3), indices);
+        }
+    }
+
+    #[test]
+    fn test_combinations_handles_edge_cases() {
+        let items = [1, 2, 3];
+        assert_eq!(
+            Combinations::new(&items, 0).collect::<Vec<_>>(),
+            vec![Vec::<i32>::new()]
+        );
+        assert_eq!(Combinations::new(&items, 3).count(), 1);
+        assert_eq!(Combinations::new(&items, 4).count(), 0);
+    }
+
+    #[test]
+    fn test_shards_cover_everything_once() {
+        let items = (0..10).collect::<Vec<_>>();
+        let all = Combinations::new(&items, 4).collect::<Vec<_>>();
+        let sharded = (0..3)
+            .flat_map(|shard| Combinations::shard(&items, 4, shard, 3))
+            .collect::<Vec<_>>();
+        assert_eq!(sharded, all);
+    }
+}
@@ -0,0 +1,187 @@
+//! Writes every simulated deal to a file as it is played, for offline
+//! analysis. Rows are written through a buffer rather than collected, so
+//! the size of a dump isn't limited by memory.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::json;
+
+use super::card::Card;
+use super::texas::Deal;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DumpFormat {
+    Csv,
+    JsonLines,
+}
+
+impl DumpFormat {
+    /// Picks JSON lines for `.jsonl` and `.ndjson` files, and CSV otherwise.
+    pub fn from_path(path: &Path) -> DumpFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") | Some("ndjson") => DumpFormat::JsonLines,
+            _ => DumpFormat::Csv,
+        }
+    }
+}
+
+const CSV_HEADER: &str =
+    "community_cards,opponent_hole_cards,category,opponent_category,outcome,pot_share";
+
+fn short_names(cards: &[Card]) -> Vec<String> {
+    cards.iter().map(|card| card.short_name()).collect()
+}
+
+fn write_deal<W: Write>(writer: &mut W, format: DumpFormat, deal: &Deal) -> io::Result<()> {
+    match format {
+        // Cards are separated by spaces, so none of the fields need quoting.
+        DumpFormat::Csv => writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            short_names(deal.community_cards).join(" "),
+            short_names(deal.opponent_hole_cards).join(" "),
+            deal.category.name(),
+            deal.opponent_category.name(),
+            deal.outcome,
+            deal.pot_share
+        ),
+        DumpFormat::JsonLines => writeln!(
+            writer,
+            "{}",
+            json!({
+                "community_cards": short_names(deal.community_cards),
+                "opponent_hole_cards": short_names(deal.opponent_hole_cards),
+                "category": deal.category.name(),
+                "opponent_category": deal.opponent_category.name(),
+                "outcome": deal.outcome,
+                "pot_share": deal.pot_share,
+            })
+        ),
+    }
+}
+
+struct State<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+/// Writes deals from any number of simulation threads. Since deals are
+/// reported while the simulation runs, write errors are remembered and
+/// returned by `finish`, and later deals are dropped.
+pub struct DealWriter<W: Write> {
+    format: DumpFormat,
+    state: Mutex<State<W>>,
+}
+
+impl DealWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let format = DumpFormat::from_path(path.as_ref());
+        DealWriter::new(BufWriter::new(File::create(path)?), format)
+    }
+}
+
+impl<W: Write> DealWriter<W> {
+    pub fn new(mut writer: W, format: DumpFormat) -> io::Result<Self> {
+        if format == DumpFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+        Ok(DealWriter {
+            format,
+            state: Mutex::new(State {
+                writer,
+                error: None,
+            }),
+        })
+    }
+
+    pub fn write(&self, deal: &Deal) {
+        let mut state = self.state.lock().expect("Deal writer lock poisoned");
+        if state.error.is_none() {
+            if let Err(e) = write_deal(&mut state.writer, self.format, deal) {
+                state.error = Some(e);
+            }
+        }
+    }
+
+    /// Flushes any buffered deals and returns the underlying writer, or the
+    /// first error we ran into.
+    pub fn finish(self) -> io::Result<W> {
+        let mut state = self.state.into_inner().expect("Deal writer lock poisoned");
+        if let Some(e) = state.error {
+            return Err(e);
+        }
+        state.writer.flush()?;
+        Ok(state.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::super::texas::simulate_with;
+    use super::{DealWriter, DumpFormat};
+
+    fn dump(format: DumpFormat) -> String {
+        let hole_cards = Card::try_vec_from("10s js").unwrap();
+        let community_cards = Card::try_vec_from("qs 9s 3d").unwrap();
+        let writer = DealWriter::new(Vec::new(), format).unwrap();
+        let results = simulate_with(
+            &hole_cards,
+            &community_cards,
+            1,
+            10,
+            &mut Xorshift::from_seed(1),
+            |deal| {
+                writer.write(deal);
+                ControlFlow::Continue(())
+            },
+        );
+        assert_eq!(results.num_iterations, 10);
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_csv_has_header_and_row_per_deal() {
+        let csv = dump(DumpFormat::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 11);
+        assert_eq!(
+            lines[0],
+            "community_cards,opponent_hole_cards,category,opponent_category,outcome,pot_share"
+        );
+        let fields = lines[1].split(',').collect::<Vec<_>>();
+        assert_eq!(fields.len(), 6);
+        assert!(fields[0].starts_with("Qs 9s 3d "));
+        assert_eq!(fields[1].split(' ').count(), 2);
+    }
+
+    #[test]
+    fn test_json_lines_has_object_per_deal() {
+        let jsonl = dump(DumpFormat::JsonLines);
+        let lines = jsonl.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 10);
+        for line in lines {
+            let deal: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(deal["community_cards"].as_array().unwrap().len(), 5);
+            assert_eq!(deal["opponent_hole_cards"].as_array().unwrap().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_format_is_picked_from_extension() {
+        use std::path::Path;
+        assert_eq!(
+            DumpFormat::from_path(Path::new("deals.csv")),
+            DumpFormat::Csv
+        );
+        assert_eq!(
+            DumpFormat::from_path(Path::new("deals.jsonl")),
+            DumpFormat::JsonLines
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fmt;
+
+use super::card::Card;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    NotAscii,
+    MissingSuit,
+    InvalidValue,
+    InvalidSuit,
+    DuplicateCard,
+}
+
+/// An error encountered while parsing one of the cards in a list of cards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// The offending token, exactly as it appeared in the input.
+    pub token: String,
+    /// The zero-based position of the token in the input.
+    pub index: usize,
+    /// A corrected version of the token, if we can guess what was meant.
+    pub suggestion: Option<String>,
+}
+
+impl ParseError {
+    pub fn new<T: AsRef<str>>(kind: ParseErrorKind, token: T) -> Self {
+        let token = token.as_ref();
+        ParseError {
+            kind,
+            token: token.to_owned(),
+            index: 0,
+            suggestion: suggest(kind, token),
+        }
+    }
+
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.index = index;
+        self
+    }
+}
+
+/// Replaces the unicode suit symbols with the letters we expect.
+fn replace_suit_symbols(token: &str) -> String {
+    token
+        .chars()
+        .map(|c| match c {
+            '♣' | '♧' => 'c',
+            '♦' | '♢' => 'd',
+            '♥' | '♡' => 'h',
+            '♠' | '♤' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+fn suggest(kind: ParseErrorKind, token: &str) -> Option<String> {
+    let candidates = match kind {
+        ParseErrorKind::NotAscii => vec![replace_suit_symbols(token)],
+        ParseErrorKind::InvalidValue | ParseErrorKind::InvalidSuit => {
+            let mut candidates = vec![];
+            if token.len() >= 2 {
+                // Maybe the suit was written before the value, e.g. "s10".
+                candidates.push(format!("{}{}", &token[1..], &token[0..1]));
+            }
+            if token.starts_with('1') || token.starts_with('0') {
+                // Maybe one of the digits of "10" was dropped, e.g. "1s".
+                candidates.push(format!("T{}", &token[token.len() - 1..]));
+            }
+            candidates
+        }
+        ParseErrorKind::MissingSuit | ParseErrorKind::DuplicateCard => vec![],
+    };
+
+    candidates
+        .into_iter()
+        .find(|candidate| Card::parse(candidate).is_ok())
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.kind {
+            ParseErrorKind::NotAscii => "must only contain ASCII characters",
+            ParseErrorKind::MissingSuit => "must contain a value followed by a suit",
+            ParseErrorKind::InvalidValue => "does not start with a valid value",
+            ParseErrorKind::InvalidSuit => "does not end with a valid suit",
+            ParseErrorKind::DuplicateCard => "appears more than once",
+        };
+        write!(
+            f,
+            "card '{}' at position {} {}",
+            self.token,
+            self.index + 1,
+            problem
+        )?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Card, ParseError, ParseErrorKind};
+
+    fn error(s: &'static str) -> ParseError {
+        Card::try_vec_from(s).unwrap_err()
+    }
+
+    #[test]
+    fn test_errors_contain_token_and_index() {
+        let err = error("2s qc 5x");
+        assert_eq!(err.kind, ParseErrorKind::InvalidSuit);
+        assert_eq!(err.token, "5x");
+        assert_eq!(err.index, 2);
+        assert_eq!(err.suggestion, None);
+        assert_eq!(
+            err.to_string(),
+            "card '5x' at position 3 does not end with a valid suit"
+        );
+    }
+
+    #[test]
+    fn test_errors_contain_suggestions() {
+        assert_eq!(error("1s").suggestion, Some(String::from("Ts")));
+        assert_eq!(error("sq").suggestion, Some(String::from("qs")));
+        assert_eq!(error("s10").suggestion, Some(String::from("10s")));
+        assert_eq!(error("K♠").suggestion, Some(String::from("Ks")));
+        assert_eq!(
+            error("1s").to_string(),
+            "card '1s' at position 1 does not start with a valid value (did you mean 'Ts'?)"
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_errors() {
+        let err = error("2s qc 2s");
+        assert_eq!(err.kind, ParseErrorKind::DuplicateCard);
+        assert_eq!(err.index, 2);
+    }
+}
@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use std::fmt;
 
 use super::card::{Card, Suit, Value};
+use super::error::ParseError;
 
 #[derive(Debug, PartialOrd, PartialEq, Eq, Ord, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// This is ordered as per the listing here:
 /// https://en.wikipedia.org/wiki/List_of_poker_hands
 pub enum Category {
@@ -19,13 +21,27 @@ pub enum Category {
     StraightFlush(Value),
 }
 
+/// The names of every category, as returned by `Category::name()`, from
+/// weakest to strongest.
+pub const CATEGORY_NAMES: [&str; 9] = [
+    "High card",
+    "One pair",
+    "Two pair",
+    "Three of a kind",
+    "Straight",
+    "Flush",
+    "Full house",
+    "Four of a kind",
+    "Straight flush",
+];
+
 fn get_kickers(hand: &Hand, non_kicker_values: Vec<Value>, max_len: usize) -> Vec<Card> {
     hand.cards
         .iter()
         .rev()
         .filter(|card| !non_kicker_values.contains(&card.value))
         .take(max_len)
-        .map(|card| *card)
+        .copied()
         .collect::<Vec<_>>()
 }
 
@@ -45,6 +61,99 @@ impl Category {
             }
         }
     }
+
+    /// Returns the name of the category without any of its values, e.g.
+    /// "Two pair".
+    pub fn name(&self) -> &'static str {
+        CATEGORY_NAMES[self.rank()]
+    }
+
+    /// Returns the weakest category of every kind, in `Category`'s ordering
+    /// from a high card up to a straight flush, e.g. to list every kind of
+    /// hand in order of strength.
+    pub fn kinds() -> Vec<Category> {
+        use Value::*;
+        let mut kinds = vec![
+            Category::HighCard(Seven),
+            Category::OnePair(Two),
+            Category::TwoPair(Three, Two),
+            Category::ThreeOfAKind(Two),
+            Category::Straight(Five),
+            Category::Flush(Seven, Five, Four, Three, Two),
+            Category::FullHouse(Two, Three),
+            Category::FourOfAKind(Two),
+            Category::StraightFlush(Five),
+        ];
+        kinds.sort();
+        kinds
+    }
+
+    /// Returns how strong the category is regardless of its values, from 0
+    /// for a high card to 8 for a straight flush. This is also the index of
+    /// its name in `CATEGORY_NAMES`.
+    pub fn rank(&self) -> usize {
+        match self {
+            Category::HighCard(_) => 0,
+            Category::OnePair(_) => 1,
+            Category::TwoPair(_, _) => 2,
+            Category::ThreeOfAKind(_) => 3,
+            Category::Straight(_) => 4,
+            Category::Flush(_, _, _, _, _) => 5,
+            Category::FullHouse(_, _) => 6,
+            Category::FourOfAKind(_) => 7,
+            Category::StraightFlush(_) => 8,
+        }
+    }
+
+    /// Describes the category along with the given hand's kickers, e.g.
+    /// "Two pair, Kings and Fives, Ace kicker".
+    pub fn describe(&self, hand: &Hand) -> String {
+        let kickers = self.get_kickers(hand);
+        let kicker_names = kickers
+            .iter()
+            .map(|card| card.value.to_string())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        match kickers.len() {
+            0 => self.to_string(),
+            1 => format!("{}, {} kicker", self, kicker_names),
+            _ => format!("{}, {} kickers", self, kicker_names),
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::HighCard(value) => write!(f, "{} high", value),
+            Category::OnePair(value) => write!(f, "One pair, {}", value.plural_name()),
+            Category::TwoPair(higher_pair, lower_pair) => write!(
+                f,
+                "Two pair, {} and {}",
+                higher_pair.plural_name(),
+                lower_pair.plural_name()
+            ),
+            Category::ThreeOfAKind(value) => {
+                write!(f, "Three of a kind, {}", value.plural_name())
+            }
+            Category::Straight(Value::Five) => write!(f, "Five-high straight (wheel)"),
+            Category::Straight(value) => write!(f, "{}-high straight", value),
+            Category::Flush(value, _, _, _, _) => write!(f, "{}-high flush", value),
+            Category::FullHouse(triplet_value, pair_value) => write!(
+                f,
+                "Full house, {} full of {}",
+                triplet_value.plural_name(),
+                pair_value.plural_name()
+            ),
+            Category::FourOfAKind(value) => write!(f, "Four of a kind, {}", value.plural_name()),
+            Category::StraightFlush(Value::Ace) => write!(f, "Royal flush"),
+            Category::StraightFlush(Value::Five) => {
+                write!(f, "Five-high straight flush (steel wheel)")
+            }
+            Category::StraightFlush(value) => write!(f, "{}-high straight flush", value),
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -57,7 +166,7 @@ pub struct Hand {
 
 impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Hand) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -129,10 +238,11 @@ impl From<Vec<Card>> for Hand {
             .into_iter()
             .map(|(suit, cards)| (cards.len(), suit, cards))
             .collect::<Vec<_>>();
-        grouped_by_suits.sort_unstable_by(|(a, _, _), (b, _, _)| a.cmp(&b));
+        // Break ties by suit, so that hands don't depend on HashMap ordering.
+        grouped_by_suits.sort_unstable_by_key(|(len, suit, _)| (*len, *suit));
 
         let mut grouped_by_values = values.into_iter().collect::<Vec<_>>();
-        grouped_by_values.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        grouped_by_values.sort_unstable_by_key(|(value, _)| *value);
 
         let mut grouped_by_n_of_a_kind = grouped_by_values
             .iter()
@@ -159,10 +269,7 @@ impl From<Vec<Card>> for Hand {
 
 impl Hand {
     pub fn highest_value(&self) -> Option<Value> {
-        match self.grouped_by_values.last() {
-            Some((v, _)) => Some(*v),
-            None => None,
-        }
+        self.grouped_by_values.last().map(|(v, _)| *v)
     }
 
     pub fn four_of_a_kind(&self) -> Option<Value> {
@@ -218,7 +325,7 @@ impl Hand {
         }
     }
 
-    fn get_straight(&self, cards: &Vec<(Value, Vec<Card>)>) -> Option<Value> {
+    fn get_straight(&self, cards: &[(Value, Vec<Card>)]) -> Option<Value> {
         let mut latest: Option<(Value, u8, usize)> = None;
         let mut has_ace = false;
 
@@ -288,26 +395,29 @@ impl Hand {
             Some(Category::TwoPair(higher_pair, lower_pair))
         } else if let Some(value) = self.one_pair() {
             Some(Category::OnePair(value))
-        } else if let Some(value) = self.highest_value() {
-            Some(Category::HighCard(value))
         } else {
-            None
+            self.highest_value().map(Category::HighCard)
         }
     }
 
     pub fn is_empty(&self) -> bool {
         self.cards.is_empty()
     }
+
+    /// Returns the hand's cards, from lowest to highest value.
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
 }
 
-impl Into<Vec<Card>> for Hand {
-    fn into(self) -> Vec<Card> {
-        self.cards
+impl From<Hand> for Vec<Card> {
+    fn from(hand: Hand) -> Vec<Card> {
+        hand.cards
     }
 }
 
 impl std::str::FromStr for Hand {
-    type Err = &'static str;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(Hand::from(Card::try_vec_from(s)?))
@@ -320,12 +430,34 @@ impl fmt::Display for Hand {
     }
 }
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::super::card::Card;
+    use super::Hand;
+
+    /// Hands are serialized as a plain list of their cards.
+    impl Serialize for Hand {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.cards.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Hand {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(Hand::from(Vec::<Card>::deserialize(deserializer)?))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
 
     use super::super::card::{Suit, Value};
-    use super::{Category, Hand};
+    use super::{Category, Hand, CATEGORY_NAMES};
 
     fn hand(value: &'static str) -> Hand {
         value.parse::<Hand>().unwrap()
@@ -464,6 +596,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_kinds_follow_category_order() {
+        let kinds = Category::kinds();
+        let ranks = kinds.iter().map(Category::rank).collect::<Vec<_>>();
+        assert_eq!(ranks, (0..CATEGORY_NAMES.len()).collect::<Vec<_>>());
+        let names = kinds.iter().map(Category::name).collect::<Vec<_>>();
+        assert_eq!(names, CATEGORY_NAMES);
+        // Even the strongest of one kind is weaker than the weakest of the
+        // next.
+        assert!(Category::OnePair(Value::Ace) < kinds[2]);
+        assert!(Category::Straight(Value::Ace) < kinds[5]);
+    }
+
+    #[test]
+    fn test_rank_indexes_category_names() {
+        for cards in ["2h 5d", "2h 2d", "3s 3c 2h 2d 4s", "5s 5h 5c 5d"].iter() {
+            let category = hand(cards).find_best_category().unwrap();
+            assert_eq!(CATEGORY_NAMES[category.rank()], category.name());
+        }
+        assert_eq!(Category::StraightFlush(Value::Six).rank(), 8);
+    }
+
+    #[test]
+    fn test_display_works() {
+        assert_eq!(Category::HighCard(Value::Ace).to_string(), "Ace high");
+        assert_eq!(Category::OnePair(Value::Six).to_string(), "One pair, Sixes");
+        assert_eq!(
+            Category::Straight(Value::Five).to_string(),
+            "Five-high straight (wheel)"
+        );
+        assert_eq!(
+            Category::FullHouse(Value::King, Value::Two).to_string(),
+            "Full house, Kings full of Twos"
+        );
+        assert_eq!(
+            Category::StraightFlush(Value::Ace).to_string(),
+            "Royal flush"
+        );
+    }
+
+    #[test]
+    fn test_describe_works() {
+        fn describe(value: &'static str) -> String {
+            let hand = hand(value);
+            hand.find_best_category().unwrap().describe(&hand)
+        }
+
+        assert_eq!(
+            describe("kh kd 5s 5c ah 2d 3c"),
+            "Two pair, Kings and Fives, Ace kicker"
+        );
+        assert_eq!(
+            describe("qs qc 10s 5s 3d"),
+            "One pair, Queens, Ten-Five-Three kickers"
+        );
+        assert_eq!(describe("ah 2h 7h 9h jh 3s"), "Ace-high flush");
+        assert_eq!(describe("as 2s 3h 4d 5h"), "Five-high straight (wheel)");
+        assert_eq!(describe("10h jh qh kh ah 2c"), "Royal flush");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_works() {
+        let json = serde_json::to_string(&hand("kh 10s")).unwrap();
+        assert_eq!(json, r#"["Ts","Kh"]"#);
+        assert_eq!(serde_json::from_str::<Hand>(&json).unwrap(), hand("kh 10s"));
+
+        let json = serde_json::to_string(&Category::TwoPair(Value::King, Value::Five)).unwrap();
+        assert_eq!(json, r#"{"TwoPair":["K","5"]}"#);
+    }
+
     #[test]
     fn test_find_best_category_works() {
         assert_eq!(hand("").find_best_category(), None);
@@ -0,0 +1,308 @@
+//! Values tournament chips in prize money with the Independent Chip Model,
+//! and uses that to decide whether to get all in.
+// https://en.wikipedia.org/wiki/Independent_Chip_Model
+
+/// The most players we'll value at once. The work doubles with each one.
+pub const MAX_PLAYERS: usize = 20;
+
+/// Returns each player's expected share of the prize money. Each place is
+/// awarded in turn, from first down, to one of the players left with a
+/// chance proportional to their stack. Players without chips are already
+/// out, and share the lowest places evenly.
+pub fn icm_equities(stacks: &[f64], payouts: &[f64]) -> Vec<f64> {
+    let num_players = stacks.len();
+    assert!(num_players <= MAX_PLAYERS, "Too many players to value");
+    let total = stacks.iter().sum::<f64>();
+
+    // `chances[taken]` is the chance that exactly the players in the
+    // bitmask `taken` finish in the top places, in any order.
+    let mut chances = vec![0.0; 1 << num_players];
+    chances[0] = 1.0;
+    let mut equities = vec![0.0; num_players];
+
+    for taken in 0..chances.len() {
+        let chance = chances[taken];
+        let place = (taken as u32).count_ones() as usize;
+        if chance == 0.0 || place >= payouts.len().min(num_players) {
+            continue;
+        }
+        let left = (0..num_players)
+            .filter(|&player| taken & (1 << player) == 0)
+            .collect::<Vec<_>>();
+        let chips_left = total
+            - (0..num_players)
+                .filter(|&player| taken & (1 << player) != 0)
+                .map(|player| stacks[player])
+                .sum::<f64>();
+
+        for &player in left.iter() {
+            let next_chance = if chips_left > 0.0 {
+                stacks[player] / chips_left
+            } else {
+                1.0 / left.len() as f64
+            };
+            equities[player] += chance * next_chance * payouts[place];
+            chances[taken | (1 << player)] += chance * next_chance;
+        }
+    }
+
+    equities
+}
+
+/// A hand where `hero` can get all in against `villain`, who covers or is
+/// covered by them. Stacks are what each player has behind, and the pot
+/// holds the blinds and antes already posted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllIn {
+    pub stacks: Vec<f64>,
+    pub hero: usize,
+    pub villain: usize,
+    pub pot: f64,
+}
+
+impl AllIn {
+    /// How much each player puts at risk, i.e. the smaller stack.
+    pub fn risk(&self) -> f64 {
+        self.stacks[self.hero].min(self.stacks[self.villain])
+    }
+
+    /// The equity we need to break even in chips, ignoring ICM, or `None`
+    /// if there are no chips at stake.
+    pub fn chip_required_equity(&self) -> Option<f64> {
+        let at_stake = 2.0 * self.risk() + self.pot;
+        if at_stake > 0.0 {
+            Some(self.risk() / at_stake)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stacks once the pot goes to `winner`, after `risk` chips
+    /// from each of us went into it.
+    fn stacks_after(&self, winner: usize, risk: f64) -> Vec<f64> {
+        let mut stacks = self.stacks.clone();
+        let loser = if winner == self.hero {
+            self.villain
+        } else {
+            self.hero
+        };
+        stacks[winner] += self.pot + risk;
+        stacks[loser] -= risk;
+        stacks
+    }
+
+    /// Returns our share of the prize money after every way the hand can
+    /// go.
+    pub fn values(&self, payouts: &[f64]) -> AllInValues {
+        let value = |stacks: Vec<f64>| icm_equities(&stacks, payouts)[self.hero];
+        AllInValues {
+            fold: value(self.stacks_after(self.villain, 0.0)),
+            steal: value(self.stacks_after(self.hero, 0.0)),
+            win: value(self.stacks_after(self.hero, self.risk())),
+            lose: value(self.stacks_after(self.villain, self.risk())),
+        }
+    }
+}
+
+/// Our expected prize money after each way an all-in hand can go.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AllInValues {
+    /// We fold and the villain takes the pot.
+    pub fold: f64,
+    /// We shove and the villain folds.
+    pub steal: f64,
+    /// We get all in and win.
+    pub win: f64,
+    /// We get all in and lose.
+    pub lose: f64,
+}
+
+impl AllInValues {
+    /// Our expected prize money from calling with the given equity.
+    pub fn call(&self, equity: f64) -> f64 {
+        equity * self.win + (1.0 - equity) * self.lose
+    }
+
+    /// Our expected prize money from shoving with the given equity, if the
+    /// villain folds with the given chance.
+    pub fn shove(&self, equity: f64, fold_chance: f64) -> f64 {
+        fold_chance * self.steal + (1.0 - fold_chance) * self.call(equity)
+    }
+
+    /// The equity at which calling is worth as much as folding, or `None`
+    /// if winning is worth no more than losing, so that no equity changes
+    /// what calling is worth.
+    pub fn required_equity(&self) -> Option<f64> {
+        if self.win > self.lose {
+            Some((self.fold - self.lose) / (self.win - self.lose))
+        } else {
+            None
+        }
+    }
+}
+
+pub fn print_icm(stacks: &[f64], payouts: &[f64], equities: &[f64]) {
+    let total_chips = stacks.iter().sum::<f64>();
+    let prize_pool = payouts.iter().sum::<f64>();
+    println!("Prize money by the Independent Chip Model:\n");
+    println!(
+        "  {:6} {:>10} {:>7} {:>10} {:>7}",
+        "Player", "Stack", "Chips", "$EV", "Prizes"
+    );
+    for (player, (stack, equity)) in stacks.iter().zip(equities.iter()).enumerate() {
+        println!(
+            "  {:<6} {:>10} {:>6.1}% {:>10.2} {:>6.1}%",
+            player + 1,
+            stack,
+            stack / total_chips * 100.0,
+            equity,
+            equity / prize_pool * 100.0
+        );
+    }
+}
+
+/// Whether to get all in, given our equity when we do.
+pub struct Decision {
+    pub all_in: AllIn,
+    pub values: AllInValues,
+    pub equity: f64,
+    /// How often the villain folds to a shove, if we're the one shoving.
+    pub fold_chance: Option<f64>,
+}
+
+impl Decision {
+    /// Our gain in prize money from calling rather than folding.
+    pub fn call_gain(&self) -> f64 {
+        self.values.call(self.equity) - self.values.fold
+    }
+
+    /// Our gain in prize money from shoving rather than folding, if we know
+    /// how often the villain folds.
+    pub fn shove_gain(&self) -> Option<f64> {
+        self.fold_chance
+            .map(|fold_chance| self.values.shove(self.equity, fold_chance) - self.values.fold)
+    }
+
+    pub fn print(&self) {
+        let all_in = &self.all_in;
+        let values = &self.values;
+        println!(
+            "Player {} all in against player {} for {} chips, with {} in the pot:\n",
+            all_in.hero + 1,
+            all_in.villain + 1,
+            all_in.risk(),
+            all_in.pot
+        );
+        // Without a required equity, calling is worth the same whatever our
+        // equity, so either any equity will do or none will.
+        let describe = |required: Option<f64>, any: bool| match required {
+            Some(equity) => format!("{:.2}%", equity * 100.0),
+            None if any => "any".to_string(),
+            None => "n/a".to_string(),
+        };
+        println!("  Our equity:              {:.2}%", self.equity * 100.0);
+        println!(
+            "  Required equity (chips): {}",
+            describe(all_in.chip_required_equity(), false)
+        );
+        println!(
+            "  Required equity (ICM):   {}",
+            describe(values.required_equity(), values.lose >= values.fold)
+        );
+        println!();
+
+        let verdict = |gain: f64| {
+            if gain > 0.0 {
+                "profitable"
+            } else {
+                "unprofitable"
+            }
+        };
+        println!("  $EV of folding:          {:.2}", values.fold);
+        println!(
+            "  $EV of calling:          {:.2} ({:+.2}, {})",
+            values.fold + self.call_gain(),
+            self.call_gain(),
+            verdict(self.call_gain())
+        );
+        if let (Some(fold_chance), Some(gain)) = (self.fold_chance, self.shove_gain()) {
+            println!(
+                "  $EV of shoving:          {:.2} ({:+.2}, {}, if they fold {:.0}% of the time)",
+                values.fold + gain,
+                gain,
+                verdict(gain),
+                fold_chance * 100.0
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{icm_equities, AllIn};
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_icm_equities_work() {
+        // Equal stacks share the prize pool evenly.
+        assert_close(
+            &icm_equities(&[10.0, 10.0, 10.0], &[50.0, 30.0, 20.0]),
+            &[100.0 / 3.0; 3],
+        );
+        // With one prize, chips are worth their face value.
+        assert_close(&icm_equities(&[60.0, 40.0], &[100.0]), &[60.0, 40.0]);
+        // The first place goes 50/30/20; the second to whoever's left.
+        let equities = icm_equities(&[50.0, 30.0, 20.0], &[70.0, 30.0]);
+        let second = |player: f64, other: f64| (other / 100.0) * player / (100.0 - other);
+        let expected_first = 0.5 * 70.0 + (second(50.0, 30.0) + second(50.0, 20.0)) * 30.0;
+        assert_close(&equities[0..1], &[expected_first]);
+        assert!((equities.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_busted_player_takes_the_lowest_place() {
+        assert_close(
+            &icm_equities(&[0.0, 50.0, 50.0], &[50.0, 30.0, 20.0]),
+            &[20.0, 40.0, 40.0],
+        );
+    }
+
+    #[test]
+    fn test_bubble_needs_more_equity_than_chips() {
+        // Four left with three paid, and the two big stacks clash.
+        let all_in = AllIn {
+            stacks: vec![40.0, 40.0, 10.0, 10.0],
+            hero: 0,
+            villain: 1,
+            pot: 0.0,
+        };
+        let values = all_in.values(&[50.0, 30.0, 20.0]);
+        assert_eq!(all_in.chip_required_equity(), Some(0.5));
+        assert!(values.required_equity().unwrap() > 0.55);
+        assert!(values.call(0.55) < values.fold);
+    }
+
+    #[test]
+    fn test_required_equity_needs_something_at_stake() {
+        // With equal payouts, winning is worth no more than losing.
+        let all_in = AllIn {
+            stacks: vec![10.0, 10.0],
+            hero: 0,
+            villain: 1,
+            pot: 0.0,
+        };
+        assert_eq!(all_in.values(&[50.0, 50.0]).required_equity(), None);
+        assert_eq!(all_in.chip_required_equity(), Some(0.5));
+
+        let busted = AllIn {
+            stacks: vec![0.0, 10.0],
+            ..all_in
+        };
+        assert_eq!(busted.chip_required_equity(), None);
+    }
+}
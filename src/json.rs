@@ -0,0 +1,525 @@
+//! Builds the documents printed by `--format json`. The schema of each
+//! document is described in the README; any incompatible change to it must
+//! bump `SCHEMA_VERSION`.
+use serde_json::{json, Map, Value};
+
+use super::card::Card;
+use super::hand::{Category, Hand, CATEGORY_NAMES};
+use super::icm::Decision;
+use super::odds::CallReport;
+use super::outs::Out;
+use super::preflop::{HeadsUpEquities, StartingHand, NUM_MATCHUPS};
+use super::pushfold::{PushFoldGame, Strategies};
+use super::rngtest::TestResult;
+use super::streets::{Street, PERCENTILES, POSITIONS};
+use super::strength::{HandClass, Strength};
+use super::texas::{Counters, CrossTab, SimulationResults, OUTCOMES};
+use super::variance::{wilson_interval, Estimate, VarianceReport};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn cards(cards: &[Card]) -> Value {
+    cards.iter().map(|card| card.short_name()).collect()
+}
+
+fn envelope(command: &str, fields: Value) -> Value {
+    let mut document = json!({
+        "schema_version": SCHEMA_VERSION,
+        "command": command,
+    });
+    if let (Some(document), Value::Object(fields)) = (document.as_object_mut(), fields) {
+        document.extend(fields);
+    }
+    document
+}
+
+fn category(hand: &Hand, category: &Category) -> Value {
+    json!({
+        "name": category.name(),
+        "description": category.describe(hand),
+        "kickers": cards(&category.get_kickers(hand)),
+    })
+}
+
+/// Reports the count of every key, even ones that never came up, along with
+/// its probability and the 95% confidence interval of that probability.
+pub fn counters(counters: &Counters, keys: &[&'static str], total: usize) -> Value {
+    let mut result = Map::new();
+    for key in keys.iter() {
+        let count = counters.get(key).copied().unwrap_or(0);
+        let (low, high) = wilson_interval(count, total);
+        result.insert(
+            String::from(*key),
+            json!({
+                "count": count,
+                "probability": if total == 0 { 0.0 } else { count as f64 / total as f64 },
+                "confidence_interval": [low, high],
+            }),
+        );
+    }
+    Value::Object(result)
+}
+
+/// Reports the counters of every row, with probabilities conditional on that
+/// row having come up.
+pub fn cross_tab(cross_tab: &CrossTab, rows: &[&'static str], columns: &[&'static str]) -> Value {
+    let empty = Counters::default();
+    let mut result = Map::new();
+    for row in rows.iter() {
+        let row_counters = cross_tab.get(row).unwrap_or(&empty);
+        let row_total = row_counters.values().sum();
+        result.insert(
+            String::from(*row),
+            counters(row_counters, columns, row_total),
+        );
+    }
+    Value::Object(result)
+}
+
+fn estimate(estimate: &Estimate) -> Value {
+    let (low, high) = estimate.confidence_interval();
+    json!({
+        "mean": estimate.mean,
+        "std_error": estimate.std_error(),
+        "confidence_interval": [low, high],
+        "samples": estimate.samples,
+    })
+}
+
+pub fn starting_hand(starting_hand: &StartingHand) -> Value {
+    json!({
+        "label": starting_hand.label(),
+        "sklansky_group": starting_hand.sklansky_group(),
+        "chen_score": starting_hand.chen_score(),
+        "equity_vs_random": starting_hand.equity_vs_random(),
+        "equity_rank": starting_hand.equity_rank(),
+    })
+}
+
+pub fn besthand(input: &[Card], hand: &Hand, best: &Category) -> Value {
+    envelope(
+        "besthand",
+        json!({
+            "input": { "cards": cards(input) },
+            "best_hand": category(hand, best),
+        }),
+    )
+}
+
+pub fn test(seed: u64, hand: &Hand, best: &Category) -> Value {
+    envelope(
+        "test",
+        json!({
+            "seed": seed,
+            "cards": cards(hand.cards()),
+            "best_hand": category(hand, best),
+        }),
+    )
+}
+
+pub struct PlayInput<'a> {
+    pub hole_cards: &'a [Card],
+    pub community_cards: &'a [Card],
+    pub opponents: usize,
+    pub iterations: usize,
+    pub rng: &'a str,
+    pub threads: usize,
+    pub seed: u64,
+}
+
+fn street(street: &Street) -> Value {
+    let mut percentiles = Map::new();
+    for &percentile in PERCENTILES.iter() {
+        percentiles.insert(percentile.to_string(), json!(street.percentile(percentile)));
+    }
+    let total = street.positions.values().sum();
+    json!({
+        "street": street.name,
+        "community_cards": street.num_community_cards,
+        "boards": street.equities.len(),
+        "games_per_board": street.games_per_board,
+        "mean_equity": street.mean_equity(),
+        "equity_percentiles": percentiles,
+        "favourite_probability": street.favourite_probability(),
+        "positions": counters(&street.positions, &POSITIONS, total),
+    })
+}
+
+pub fn play(
+    input: &PlayInput,
+    results: &SimulationResults,
+    variance: Option<&VarianceReport>,
+    streets: Option<&[Street]>,
+    call: Option<&CallReport>,
+) -> Value {
+    let total = results.num_iterations;
+    let mut document = envelope(
+        "play",
+        json!({
+            "input": {
+                "hole_cards": cards(input.hole_cards),
+                "community_cards": cards(input.community_cards),
+                "opponents": input.opponents,
+                "iterations": input.iterations,
+                "rng": input.rng,
+                "threads": input.threads,
+            },
+            "seed": input.seed,
+            "starting_hand": starting_hand(&StartingHand::new(
+                input.hole_cards[0],
+                input.hole_cards[1],
+            )),
+            "iterations": total,
+            "stopped_early": total < input.iterations,
+            "hand_categories": counters(&results.hand_categories, &CATEGORY_NAMES, total),
+            "opponent_hand_categories":
+                counters(&results.opponent_hand_categories, &CATEGORY_NAMES, total),
+            "outcomes": counters(&results.game_outcomes, &OUTCOMES, total),
+            "equity": estimate(&results.equity()),
+            "outcomes_by_category":
+                cross_tab(&results.outcomes_by_category, &CATEGORY_NAMES, &OUTCOMES),
+            "opponent_categories_by_category": cross_tab(
+                &results.opponent_categories_by_category,
+                &CATEGORY_NAMES,
+                &CATEGORY_NAMES,
+            ),
+        }),
+    );
+
+    if let (Some(streets), Some(fields)) = (streets, document.as_object_mut()) {
+        fields.insert(
+            String::from("streets"),
+            streets.iter().map(street).collect(),
+        );
+    }
+
+    if let (Some(call), Some(fields)) = (call, document.as_object_mut()) {
+        let odds = &call.odds;
+        let ev = call.ev();
+        let (low, high) = ev.confidence_interval();
+        fields.insert(
+            String::from("pot_odds"),
+            json!({
+                "pot": odds.pot,
+                "to_call": odds.to_call,
+                "stack": odds.stack,
+                "big_blind": odds.big_blind,
+                "call_amount": odds.call_amount(),
+                "all_in": odds.is_all_in(),
+                "odds_against": odds.odds_against(),
+                "required_equity": odds.required_equity(),
+                "stack_to_pot": odds.stack_to_pot(),
+                "equity": estimate(&call.equity),
+                "exact": call.exact,
+                "call_ev": {
+                    "chips": ev.mean,
+                    "big_blinds": odds.in_big_blinds(ev.mean),
+                    "std_error": ev.std_error(),
+                    "confidence_interval": [low, high],
+                },
+                "decision": call.decision(),
+                "implied_odds": call.implied.as_ref().map(|implied| json!({
+                    "future_bets": implied.future_bets,
+                    "implied_gain": implied.implied_gain(),
+                    "reverse_implied_cost": implied.reverse_implied_cost(),
+                    "call_ev": call.implied_ev(),
+                    "future_bets_needed": implied.future_bets_needed(ev.mean),
+                    "profitable_on_implied_odds": call.profitable_on_implied_odds(),
+                    "reverse_implied_odds": call.has_reverse_implied_odds(),
+                })),
+            }),
+        );
+    }
+
+    if let (Some(report), Some(fields)) = (variance, document.as_object_mut()) {
+        fields.insert(
+            String::from("variance_reduction"),
+            variance_reduction(report),
+        );
+    }
+
+    document
+}
+
+/// Each estimate's fields sit alongside its `relative_variance`, as the
+/// README documents.
+fn variance_reduction(report: &VarianceReport) -> Value {
+    let optional_estimate = |value: &Option<Estimate>| match value {
+        Some(value) => {
+            let mut fields = estimate(value);
+            fields["relative_variance"] = json!(report.relative_variance(value));
+            fields
+        }
+        None => Value::Null,
+    };
+    json!({
+        "plain": estimate(&report.plain),
+        "stratified": optional_estimate(&report.stratified),
+        "optimally_allocated": optional_estimate(&report.optimally_allocated),
+        "comparison": report.comparison.as_ref().map(|comparison| json!({
+            "other_hole_cards": cards(&comparison.other_hole_cards),
+            "difference": estimate(&comparison.difference),
+            "relative_variance": comparison.relative_variance(),
+        })),
+    })
+}
+
+pub struct OutsInput<'a> {
+    pub hole_cards: &'a [Card],
+    pub community_cards: &'a [Card],
+    pub iterations: usize,
+    pub rng: &'a str,
+    pub seed: u64,
+}
+
+pub fn outs(input: &OutsInput, outs: &[Out]) -> Value {
+    let hand = Hand::from([input.community_cards, input.hole_cards].concat());
+    let best = hand
+        .find_best_category()
+        .expect("a hand with hole cards is non-empty");
+    let out = |out: &Out| {
+        json!({
+            "card": out.card.short_name(),
+            "category": out.category.name(),
+            "description": out.description,
+            "win_rate": out.win_rate,
+            "tie_rate": out.tie_rate,
+            "equity": out.equity(),
+            "opponents_helped": out.opponents_helped,
+        })
+    };
+    envelope(
+        "outs",
+        json!({
+            "input": {
+                "hole_cards": cards(input.hole_cards),
+                "community_cards": cards(input.community_cards),
+                "iterations": input.iterations,
+                "rng": input.rng,
+            },
+            "seed": input.seed,
+            "current_hand": category(&hand, &best),
+            "clean_outs": outs.iter().filter(|o| o.is_clean()).map(out).collect::<Vec<_>>(),
+            "tainted_outs": outs.iter().filter(|o| !o.is_clean()).map(out).collect::<Vec<_>>(),
+        }),
+    )
+}
+
+pub fn icm(
+    stacks: &[f64],
+    payouts: &[f64],
+    equities: &[f64],
+    decision: Option<&Decision>,
+    seed: Option<u64>,
+) -> Value {
+    let decision = decision.map(|decision| {
+        let values = &decision.values;
+        json!({
+            "hero": decision.all_in.hero + 1,
+            "villain": decision.all_in.villain + 1,
+            "pot": decision.all_in.pot,
+            "risk": decision.all_in.risk(),
+            "equity": decision.equity,
+            "chip_required_equity": decision.all_in.chip_required_equity(),
+            "icm_required_equity": values.required_equity(),
+            "values": {
+                "fold": values.fold,
+                "steal": values.steal,
+                "win": values.win,
+                "lose": values.lose,
+            },
+            "call_gain": decision.call_gain(),
+            "fold_chance": decision.fold_chance,
+            "shove_gain": decision.shove_gain(),
+        })
+    });
+    envelope(
+        "icm",
+        json!({
+            "input": {
+                "stacks": stacks,
+                "payouts": payouts,
+            },
+            "seed": seed,
+            "equities": equities,
+            "decision": decision,
+        }),
+    )
+}
+
+pub struct PushFoldInput<'a> {
+    pub game: &'a PushFoldGame,
+    /// `None` if every matchup was worked out exactly.
+    pub games_per_matchup: Option<usize>,
+    /// The precomputed table the equities came from, if any.
+    pub table: Option<&'a str>,
+    pub rng: &'a str,
+    /// Only set when the equities were simulated for this run.
+    pub seed: Option<u64>,
+}
+
+pub fn pushfold(input: &PushFoldInput, strategies: &Strategies) -> Value {
+    let range = |frequencies: &[f64]| {
+        let mut result = Map::new();
+        for (hand, frequency) in StartingHand::all().iter().zip(frequencies.iter()) {
+            result.insert(hand.label(), json!(frequency));
+        }
+        json!({
+            "size": Strategies::range_size(frequencies),
+            "frequencies": result,
+        })
+    };
+    envelope(
+        "pushfold",
+        json!({
+            "input": {
+                "stack": input.game.stack,
+                "ante": input.game.ante,
+                "players": input.game.num_players,
+                "games_per_matchup": input.games_per_matchup,
+                "table": input.table,
+                "rng": input.rng,
+            },
+            "seed": input.seed,
+            "push": range(&strategies.push),
+            "call": range(&strategies.call),
+            "exploitability": strategies.exploitability,
+        }),
+    )
+}
+
+pub fn precompute_preflop(path: &str, equities: &HeadsUpEquities, seed: Option<u64>) -> Value {
+    envelope(
+        "precompute",
+        json!({
+            "table": "preflop",
+            "path": path,
+            "matchups": NUM_MATCHUPS,
+            "games_per_matchup": equities.games_per_matchup,
+            "seed": seed,
+        }),
+    )
+}
+
+pub fn strength(hole_cards: &[Card], community_cards: &[Card], strength: &Strength) -> Value {
+    let class = |class: &HandClass| {
+        json!({
+            "description": class.description,
+            "example": cards(&class.example),
+            "combos": class.num_combos,
+            "opponent_combos": class.num_opponent_combos,
+        })
+    };
+    let hand = Hand::from([community_cards, hole_cards].concat());
+    let best = hand
+        .find_best_category()
+        .expect("a hand with hole cards is non-empty");
+    envelope(
+        "strength",
+        json!({
+            "input": {
+                "hole_cards": cards(hole_cards),
+                "community_cards": cards(community_cards),
+            },
+            "current_hand": category(&hand, &best),
+            "combos": strength.num_combos,
+            "beaten_by": strength.beaten_by,
+            "tied_by": strength.tied_by,
+            "beats": strength.beats,
+            "percentile": strength.percentile(),
+            "nuts_rank": strength.nuts_rank(),
+            "nuts": class(&strength.nuts),
+            "stronger_hands": strength.stronger_hands.iter().map(class).collect::<Vec<_>>(),
+        }),
+    )
+}
+
+pub fn rngtest(
+    results: &[TestResult],
+    rng: &str,
+    seed: u64,
+    trials: usize,
+    max_z_score: f64,
+) -> Value {
+    let tests = results
+        .iter()
+        .map(|result| {
+            json!({
+                "name": result.name,
+                "chi_square": result.chi_square,
+                "degrees_of_freedom": result.degrees_of_freedom,
+                "z_score": result.z_score(),
+                "passed": result.passed(max_z_score),
+            })
+        })
+        .collect::<Vec<_>>();
+    envelope(
+        "rngtest",
+        json!({
+            "input": { "rng": rng, "trials": trials, "max_z_score": max_z_score },
+            "seed": seed,
+            "tests": tests,
+            "passed": results.iter().all(|result| result.passed(max_z_score)),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::card::Card;
+    use super::super::hand::Hand;
+    use super::super::variance::{Comparison, Estimate, VarianceReport};
+    use super::{besthand, variance_reduction};
+
+    #[test]
+    fn test_besthand_works() {
+        let input = Card::try_vec_from("kh kd 5s 5c ah").unwrap();
+        let hand = Hand::from(input.clone());
+        let best = hand.find_best_category().unwrap();
+        let document = besthand(&input, &hand, &best);
+        assert_eq!(document["schema_version"], 1);
+        assert_eq!(document["command"], "besthand");
+        assert_eq!(document["input"]["cards"][0], "Kh");
+        assert_eq!(document["best_hand"]["name"], "Two pair");
+        assert_eq!(document["best_hand"]["kickers"][0], "Ah");
+    }
+
+    #[test]
+    fn test_variance_reduction_has_the_documented_keys() {
+        let estimate = Estimate {
+            mean: 0.6,
+            variance: 0.0004,
+            samples: 1000,
+        };
+        let report = VarianceReport {
+            hole_cards: Card::try_vec_from("as ks").unwrap(),
+            plain: estimate,
+            stratified: Some(Estimate {
+                variance: 0.0002,
+                ..estimate
+            }),
+            optimally_allocated: None,
+            comparison: Some(Comparison {
+                other_hole_cards: Card::try_vec_from("qs qh").unwrap(),
+                difference: estimate,
+                independent_variance: 0.0008,
+            }),
+        };
+        let document = variance_reduction(&report);
+
+        let estimate_keys = ["mean", "std_error", "confidence_interval", "samples"];
+        for key in estimate_keys.iter() {
+            assert!(!document["plain"][key].is_null(), "plain.{}", key);
+            assert!(!document["stratified"][key].is_null(), "stratified.{}", key);
+            assert!(
+                !document["comparison"]["difference"][key].is_null(),
+                "comparison.difference.{}",
+                key
+            );
+        }
+        assert_eq!(document["stratified"]["relative_variance"], 0.5);
+        assert!(document["optimally_allocated"].is_null());
+        assert_eq!(document["comparison"]["other_hole_cards"][0], "Qs");
+        assert_eq!(document["comparison"]["relative_variance"], 0.5);
+    }
+}
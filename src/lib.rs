@@ -0,0 +1,18 @@
+pub mod card;
+pub mod combinations;
+pub mod dump;
+pub mod error;
+pub mod hand;
+pub mod icm;
+pub mod json;
+pub mod odds;
+pub mod outs;
+pub mod preflop;
+pub mod progress;
+pub mod pushfold;
+pub mod random;
+pub mod rngtest;
+pub mod streets;
+pub mod strength;
+pub mod texas;
+pub mod variance;
@@ -1,14 +1,26 @@
-#[macro_use(value_t, values_t)]
+#[macro_use(value_t)]
 extern crate clap;
 
-use clap::{App, Arg, SubCommand};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
-mod card;
-mod hand;
-mod random;
-mod texas;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use theb::card::Card;
+use theb::random::{derive_seed, FromSeed, Random, Rng, Xorshift};
 
-const AFTER_HELP: &'static str = "\
+use theb::dump::DealWriter;
+use theb::hand::Hand;
+use theb::odds::{CallReport, FutureBetTally, PotOdds};
+use theb::preflop::{HeadsUpEquities, StartingHand, NUM_DISTINCT_BOARDS, NUM_MATCHUPS};
+use theb::progress::Progress;
+use theb::variance::Estimate;
+use theb::{icm, json, outs, pushfold, rngtest, streets, strength, texas, variance};
+
+const AFTER_HELP: &str = "\
 EXAMPLES:
     Find the best possible hand for a Two of Spades, Three of Diamonds, Jack of Hearts,
     Queen of Spades, and King of Clubs:
@@ -23,18 +35,751 @@ EXAMPLES:
     Three of Diamonds in the mix:
 
         theb play 10s js qs 9s 3d
+
+    Write every simulated deal to a CSV file for offline analysis:
+
+        theb play 10s js --dump deals.csv
+
+    Work out whether calling a bet of 50 into a pot of 150 is profitable with a
+    flush draw, with blinds of 1 and 2:
+
+        theb play as ks 7s 2s 9d --pot 150 --to-call 50 --big-blind 2
+
+    Check whether a draw too weak to call on pot odds alone can call anyway, if a
+    pot-sized bet on the river pays us off when we hit:
+
+        theb play 6s 5s 7s 8d 2s kc --pot 100 --to-call 80 --future-bets 1
+
+    List the cards that would improve Ace-King of Spades on a flop with two spades:
+
+        theb outs as ks 7s 2s 9d
+
+    Count how many of the possible opponent holdings beat a set of sevens, and
+    find out what the nuts are:
+
+        theb strength 7h 7d 9c 7s 2h
+
+    Find the heads-up ranges to shove and call with at equilibrium with 10 big
+    blinds:
+
+        theb pushfold --stack 10bb
+
+    Value three stacks on a final table paying 50, 30 and 20, and decide whether
+    player 1 should call an all in from player 2 with Ace-King:
+
+        theb icm --stacks 5000 3000 2000 --payouts 50 30 20 --hero 1 --villain 2 as ks
+
+    Work out the exact equity of every preflop matchup once, so that pushfold
+    and icm can look up equities instead of simulating them:
+
+        theb precompute preflop
+
+    Watch the equity estimate converge during a long simulation. Pressing Ctrl-C
+    stops it early and prints the results so far:
+
+        theb play 10s js --times 10000000 --live
+
+    Repeat a simulation exactly by passing the random seed it reported:
+
+        theb --seed 1234 play 10s js
 ";
 
+fn parse_cards(matches: &ArgMatches) -> Vec<Card> {
+    let tokens = matches.values_of("CARD").unwrap_or_default();
+    Card::try_vec_from_tokens(tokens).unwrap_or_else(|e| {
+        clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue).exit()
+    })
+}
+
+/// Parses a positive number of chips from the given argument.
+fn parse_chips(matches: &ArgMatches, name: &str) -> f64 {
+    match value_t!(matches.value_of(name), f64) {
+        Ok(chips) if chips > 0.0 && chips.is_finite() => chips,
+        Ok(_) => clap::Error::with_description(
+            &format!("--{} must be a positive number of chips", name),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Err(e) => e.exit(),
+    }
+}
+
+fn rng_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("rng")
+        .long("rng")
+        .value_name("GENERATOR")
+        .possible_values(&["lcg", "xorshift"])
+        .default_value("lcg")
+        .help("Random number generator to use")
+        .takes_value(true)
+}
+
+/// Where `precompute preflop` writes its table, and where other commands look
+/// for one when no `--table` is given.
+const DEFAULT_PREFLOP_TABLE: &str = "preflop-equities.bin";
+
+fn table_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("table")
+        .long("table")
+        .value_name("FILE")
+        .help("A preflop equity table written by 'theb precompute preflop'. Defaults to 'preflop-equities.bin' if it exists.")
+        .takes_value(true)
+}
+
+/// Loads the preflop equity table named by `--table`, or the default one if
+/// it exists, along with the path it came from.
+fn load_preflop_table<'a>(matches: &'a ArgMatches) -> Option<(&'a str, HeadsUpEquities)> {
+    let path = match matches.value_of("table") {
+        Some(path) => path,
+        None if Path::new(DEFAULT_PREFLOP_TABLE).exists() => DEFAULT_PREFLOP_TABLE,
+        None => return None,
+    };
+    let equities = HeadsUpEquities::load(path).unwrap_or_else(|e| {
+        clap::Error::with_description(
+            &format!("Couldn't load '{}': {}", path, e),
+            clap::ErrorKind::Io,
+        )
+        .exit()
+    });
+    Some((path, equities))
+}
+
+fn print_json(document: &serde_json::Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(document).expect("JSON values always serialize")
+    );
+}
+
+/// Runs the simulation while redrawing its progress on stderr, optionally
+/// with the equity estimate so far, until it finishes.
+fn with_progress<T, F: FnOnce() -> T>(
+    progress: &Progress,
+    with_equity: bool,
+    interval: Duration,
+    simulate: F,
+) -> T {
+    let (done, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                eprint!("\r\x1b[K{}", progress.status(with_equity));
+            }
+            eprint!("\r\x1b[K");
+        });
+        let result = simulate();
+        drop(done);
+        result
+    })
+}
+
+fn play<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) {
+    let cards = parse_cards(matches);
+    let hole_cards = cards[0..texas::NUM_HOLE_CARDS].to_owned();
+    let community_cards = cards[texas::NUM_HOLE_CARDS..].to_owned();
+    let times = parse_times(matches);
+    let opponents = match value_t!(matches.value_of("opponents"), usize) {
+        Ok(opponents) if (1..=texas::MAX_OPPONENTS).contains(&opponents) => opponents,
+        Ok(_) => clap::Error::with_description(
+            &format!(
+                "The number of opponents must be between 1 and {}",
+                texas::MAX_OPPONENTS
+            ),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Err(e) => e.exit(),
+    };
+    let threads = parse_threads(matches);
+    let compare_to = matches.values_of("compare").map(|tokens| {
+        let other_hole_cards = Card::try_vec_from_tokens(tokens).unwrap_or_else(|e| {
+            clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue).exit()
+        });
+        if let Some(card) = other_hole_cards.iter().find(|card| cards.contains(card)) {
+            clap::Error::with_description(
+                &format!("'{}' is already one of the cards being played", card),
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }
+        other_hole_cards
+    });
+    let pot_odds = matches.value_of("pot").map(|_| PotOdds {
+        pot: parse_chips(matches, "pot"),
+        to_call: parse_chips(matches, "to-call"),
+        stack: matches
+            .value_of("stack")
+            .map(|_| parse_chips(matches, "stack")),
+        big_blind: parse_chips(matches, "big-blind"),
+    });
+    let future_bet_fraction = matches.value_of("future-bets").map(|_| {
+        match value_t!(matches.value_of("future-bets"), f64) {
+            Ok(fraction) if fraction > 0.0 && fraction.is_finite() => fraction,
+            Ok(_) => clap::Error::with_description(
+                "--future-bets must be a positive fraction of the pot",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit(),
+            Err(e) => e.exit(),
+        }
+    });
+    let future_bet_tally = future_bet_fraction.map(|_| FutureBetTally::default());
+
+    let progress = Arc::new(Progress::new(times));
+    let handler_progress = Arc::clone(&progress);
+    ctrlc::set_handler(move || {
+        // Stop the simulation and any reports the first time, so that we can
+        // still print the results so far, but exit if that's already
+        // happened.
+        if handler_progress.is_stopped() {
+            std::process::exit(130);
+        }
+        handler_progress.stop();
+    })
+    .expect("Couldn't install the Ctrl-C handler");
+
+    let report = || {
+        if matches.is_present("variance") || compare_to.is_some() {
+            // Use a different stream than any of the simulation threads.
+            let mut random = R::from_seed(derive_seed(seed, threads as u64));
+            variance::variance_report_with(
+                &hole_cards,
+                &community_cards,
+                compare_to.as_deref(),
+                times,
+                &mut random,
+                || progress.is_stopped(),
+            )
+        } else {
+            None
+        }
+    };
+
+    let progression = || {
+        if matches.is_present("streets") {
+            // Split the games evenly between boards and games on each board.
+            let games_per_board = ((times as f64).sqrt().ceil() as usize).max(1);
+            let mut random = R::from_seed(derive_seed(seed, threads as u64 + 1));
+            streets::progression_with(
+                &hole_cards,
+                &community_cards,
+                games_per_board,
+                games_per_board,
+                &mut random,
+                || progress.is_stopped(),
+            )
+        } else {
+            None
+        }
+    };
+
+    let dump_path = matches.value_of("dump");
+    let dump = dump_path.map(|path| {
+        DealWriter::create(path).unwrap_or_else(|e| {
+            clap::Error::with_description(
+                &format!("Couldn't create '{}': {}", path, e),
+                clap::ErrorKind::Io,
+            )
+            .exit()
+        })
+    });
+
+    let live = matches.is_present("live");
+    let show_progress = live || std::io::stderr().is_terminal();
+    let interval = match value_t!(matches.value_of("interval"), u64) {
+        Ok(0) => clap::Error::with_description(
+            "--interval must be at least 1 millisecond",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Ok(interval) => Duration::from_millis(interval),
+        Err(e) => e.exit(),
+    };
+
+    let new_on_deal = || {
+        let dump = dump.as_ref();
+        let mut tally = future_bet_tally.as_ref().map(FutureBetTally::local);
+        let mut progress = progress.local();
+        move |deal: &texas::Deal| {
+            if let Some(dump) = dump {
+                dump.write(deal);
+            }
+            if let Some(tally) = &mut tally {
+                tally.record(deal);
+            }
+            progress.record(deal)
+        }
+    };
+
+    if !as_json {
+        texas::print_header(&hole_cards, &community_cards, seed, threads);
+    }
+
+    let simulate = || {
+        texas::simulate_in_parallel_with::<R, _, _>(
+            &hole_cards,
+            &community_cards,
+            opponents,
+            times,
+            seed,
+            threads,
+            &new_on_deal,
+        )
+    };
+    let results = if show_progress {
+        with_progress(&progress, live, interval, simulate)
+    } else {
+        simulate()
+    };
+
+    // Either report is skipped if we're asked to stop before it's done.
+    let (report, progression) = (report(), progression());
+
+    let call = pot_odds.map(|odds| {
+        let implied =
+            future_bet_tally
+                .as_ref()
+                .zip(future_bet_fraction)
+                .map(|(tally, fraction)| {
+                    let made_hand = Hand::from(cards.clone())
+                        .find_best_category()
+                        .is_some_and(|category| category.rank() > 0);
+                    tally.implied_odds(odds.future_bets(fraction, community_cards.len()), made_hand)
+                });
+        // On the river against one opponent we can count our equity exactly.
+        if opponents == 1 && community_cards.len() == texas::NUM_COMMUNITY_CARDS {
+            let found = strength::hand_strength(&hole_cards, &community_cards);
+            CallReport {
+                odds,
+                equity: Estimate {
+                    mean: found.percentile(),
+                    variance: 0.0,
+                    samples: found.num_combos,
+                },
+                exact: true,
+                implied,
+            }
+        } else {
+            CallReport {
+                odds,
+                equity: results.equity(),
+                exact: false,
+                implied,
+            }
+        }
+    });
+
+    if as_json {
+        let input = json::PlayInput {
+            hole_cards: &hole_cards,
+            community_cards: &community_cards,
+            opponents,
+            iterations: times,
+            rng: matches.value_of("rng").unwrap_or("lcg"),
+            threads,
+            seed,
+        };
+        print_json(&json::play(
+            &input,
+            &results,
+            report.as_ref(),
+            progression.as_deref(),
+            call.as_ref(),
+        ));
+    } else {
+        texas::print_results(&results, &community_cards, opponents, times);
+
+        if let Some(call) = call {
+            println!();
+            call.print();
+        }
+
+        if let Some(progression) = progression {
+            println!();
+            streets::print_progression(&progression);
+        }
+
+        if let Some(report) = report {
+            println!();
+            report.print();
+        }
+    }
+
+    if let (Some(dump), Some(path)) = (dump, dump_path) {
+        if let Err(e) = dump.finish() {
+            eprintln!("Couldn't write deals to '{}': {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parses `--times`, which must be at least 1 since every estimate divides
+/// by it.
+fn parse_times(matches: &ArgMatches) -> usize {
+    match value_t!(matches.value_of("times"), usize) {
+        Ok(0) => clap::Error::with_description(
+            "--times must be at least 1",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Ok(times) => times,
+        Err(e) => e.exit(),
+    }
+}
+
+/// Parses `--threads`, where 0 means one thread for every CPU.
+fn parse_threads(matches: &ArgMatches) -> usize {
+    match value_t!(matches.value_of("threads"), usize) {
+        Ok(0) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        Ok(threads) => threads,
+        Err(e) => e.exit(),
+    }
+}
+
+/// Parses a number of big blinds, with or without a "bb" suffix.
+fn parse_big_blinds(matches: &ArgMatches, name: &str) -> f64 {
+    let value = matches.value_of(name).unwrap_or_default();
+    let number = value
+        .strip_suffix("bb")
+        .or_else(|| value.strip_suffix("BB"))
+        .unwrap_or(value);
+    match number.parse::<f64>() {
+        Ok(big_blinds) if big_blinds >= 0.0 && big_blinds.is_finite() => big_blinds,
+        _ => clap::Error::with_description(
+            &format!("--{} must be a number of big blinds, like '10bb'", name),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+    }
+}
+
+fn run_pushfold<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) {
+    let times = parse_times(matches);
+    let threads = parse_threads(matches);
+    let num_players = match value_t!(matches.value_of("players"), usize) {
+        Ok(players) if (2..=texas::MAX_OPPONENTS + 1).contains(&players) => players,
+        Ok(_) => clap::Error::with_description(
+            &format!(
+                "The number of players must be between 2 and {}",
+                texas::MAX_OPPONENTS + 1
+            ),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Err(e) => e.exit(),
+    };
+    let game = pushfold::PushFoldGame {
+        stack: parse_big_blinds(matches, "stack"),
+        ante: parse_big_blinds(matches, "ante"),
+        num_players,
+    };
+    if game.stack <= 1.0 + game.ante {
+        clap::Error::with_description(
+            "--stack must be more than the big blind and ante",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit()
+    }
+
+    let (table, equities) = match load_preflop_table(matches) {
+        Some((path, equities)) => (Some(path), equities),
+        None => (
+            None,
+            HeadsUpEquities::simulate_in_parallel::<R, _>(times, seed, threads, || ()),
+        ),
+    };
+    let strategies = pushfold::solve(&game, &equities, pushfold::NUM_SOLVER_ITERATIONS);
+    // Only equities simulated for this run depend on the seed.
+    let seed = if table.is_none() { Some(seed) } else { None };
+    if as_json {
+        let input = json::PushFoldInput {
+            game: &game,
+            games_per_matchup: equities.games_per_matchup,
+            table,
+            rng: matches.value_of("rng").unwrap_or("lcg"),
+            seed,
+        };
+        print_json(&json::pushfold(&input, &strategies));
+    } else {
+        pushfold::print_strategies(&game, &strategies, equities.games_per_matchup);
+        if let Some(path) = table {
+            println!("Equities from the preflop table in '{}'.", path);
+        }
+        if let Some(seed) = seed {
+            println!("Random seed:\n  {}", seed);
+        }
+    }
+}
+
+fn run_precompute_preflop<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) {
+    let path = matches.value_of("output").unwrap_or(DEFAULT_PREFLOP_TABLE);
+    let threads = parse_threads(matches);
+
+    // Simulating works through the matchups one at a time, while dealing
+    // every board works through the boards one at a time.
+    let times = matches.value_of("times").map(|_| parse_times(matches));
+    let (total, units) = match times {
+        Some(_) => (NUM_MATCHUPS, "matchups"),
+        None => (NUM_DISTINCT_BOARDS, "boards"),
+    };
+    let show_progress = std::io::stderr().is_terminal();
+    let completed = AtomicUsize::new(0);
+    let started = Instant::now();
+    let on_progress = || {
+        let completed = completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if show_progress {
+            let elapsed = started.elapsed().as_secs_f64();
+            let remaining = (elapsed / completed as f64 * (total - completed) as f64) as u64;
+            eprint!(
+                "\r\x1b[K{} of {} {} ({:.1}%), {}:{:02}:{:02} left",
+                completed,
+                total,
+                units,
+                completed as f64 / total as f64 * 100.0,
+                remaining / 3600,
+                remaining / 60 % 60,
+                remaining % 60
+            );
+        }
+    };
+    let equities = match times {
+        Some(times) => {
+            HeadsUpEquities::simulate_in_parallel::<R, _>(times, seed, threads, on_progress)
+        }
+        None => HeadsUpEquities::enumerate(threads, on_progress),
+    };
+    if show_progress {
+        eprint!("\r\x1b[K");
+    }
+
+    equities.save(path).unwrap_or_else(|e| {
+        clap::Error::with_description(
+            &format!("Couldn't write '{}': {}", path, e),
+            clap::ErrorKind::Io,
+        )
+        .exit()
+    });
+
+    let seed = equities.games_per_matchup.map(|_| seed);
+    if as_json {
+        print_json(&json::precompute_preflop(path, &equities, seed));
+    } else {
+        let method = match equities.games_per_matchup {
+            Some(games) => format!("from {} games each", games),
+            None => String::from("exactly"),
+        };
+        println!(
+            "Wrote the equities of all {} preflop matchups, worked out {}, to '{}'.",
+            NUM_MATCHUPS, method, path
+        );
+        if let Some(seed) = seed {
+            println!("Random seed:\n  {}", seed);
+        }
+    }
+}
+
+/// Parses every value of the given argument as a non-negative number.
+fn parse_amounts(matches: &ArgMatches, name: &str) -> Vec<f64> {
+    matches
+        .values_of(name)
+        .unwrap_or_default()
+        .map(|value| match value.parse::<f64>() {
+            Ok(amount) if amount >= 0.0 && amount.is_finite() => amount,
+            _ => clap::Error::with_description(
+                &format!("'{}' isn't a valid amount for --{}", value, name),
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit(),
+        })
+        .collect()
+}
+
+/// Parses a player's number, counting from 1, into an index.
+fn parse_player(matches: &ArgMatches, name: &str, num_players: usize) -> usize {
+    match value_t!(matches.value_of(name), usize) {
+        Ok(player) if (1..=num_players).contains(&player) => player - 1,
+        Ok(_) => clap::Error::with_description(
+            &format!("--{} must be a player from 1 to {}", name, num_players),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        Err(e) => e.exit(),
+    }
+}
+
+fn run_icm<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) {
+    let stacks = parse_amounts(matches, "stacks");
+    let payouts = parse_amounts(matches, "payouts");
+    if stacks.len() > icm::MAX_PLAYERS {
+        clap::Error::with_description(
+            &format!("There can be at most {} players", icm::MAX_PLAYERS),
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit()
+    }
+    let equities = icm::icm_equities(&stacks, &payouts);
+    let table = matches
+        .value_of("CARD")
+        .and_then(|_| load_preflop_table(matches));
+
+    let decision = matches.value_of("hero").map(|_| {
+        let hero = parse_player(matches, "hero", stacks.len());
+        let villain = parse_player(matches, "villain", stacks.len());
+        if hero == villain {
+            clap::Error::with_description(
+                "--hero and --villain must be different players",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }
+        let all_in = icm::AllIn {
+            stacks: stacks.clone(),
+            hero,
+            villain,
+            pot: parse_amounts(matches, "pot")[0],
+        };
+        if all_in.risk() == 0.0 {
+            clap::Error::with_description(
+                "--hero and --villain must both have chips to get all in",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }
+        let equity = if matches.is_present("equity") {
+            parse_amounts(matches, "equity")[0]
+        } else if matches.is_present("CARD") {
+            let hole_cards = parse_cards(matches);
+            match &table {
+                Some((_, equities)) => {
+                    equities.against_random(StartingHand::new(hole_cards[0], hole_cards[1]))
+                }
+                None => {
+                    let times = parse_times(matches);
+                    texas::simulate(&hole_cards, &[], times, &mut R::from_seed(seed))
+                        .equity()
+                        .mean
+                }
+            }
+        } else {
+            clap::Error::with_description(
+                "Give either our hole cards or --equity to decide whether to get all in",
+                clap::ErrorKind::MissingRequiredArgument,
+            )
+            .exit()
+        };
+        let fold_chance = matches
+            .value_of("fold-chance")
+            .map(|_| parse_amounts(matches, "fold-chance")[0]);
+        if equity > 1.0 || fold_chance.is_some_and(|chance| chance > 1.0) {
+            clap::Error::with_description(
+                "--equity and --fold-chance must be between 0 and 1",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }
+        icm::Decision {
+            values: all_in.values(&payouts),
+            all_in,
+            equity,
+            fold_chance,
+        }
+    });
+
+    // Only a simulated equity depends on the seed.
+    let simulated = decision.is_some() && matches.is_present("CARD") && table.is_none();
+    let looked_up = decision.is_some() && matches.is_present("CARD") && table.is_some();
+    if as_json {
+        let seed = if simulated { Some(seed) } else { None };
+        print_json(&json::icm(
+            &stacks,
+            &payouts,
+            &equities,
+            decision.as_ref(),
+            seed,
+        ));
+    } else {
+        icm::print_icm(&stacks, &payouts, &equities);
+        if let Some(decision) = decision {
+            println!();
+            decision.print();
+        }
+        if simulated {
+            println!();
+            println!(
+                "Our equity is against a random hand, from {} games with random seed {}.",
+                matches.value_of("times").unwrap_or_default(),
+                seed
+            );
+        } else if looked_up {
+            println!();
+            println!(
+                "Our equity is against a random hand, from the preflop table in '{}'.",
+                table.map(|(path, _)| path).unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn run_outs<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) {
+    let cards = parse_cards(matches);
+    let (hole_cards, community_cards) = cards.split_at(texas::NUM_HOLE_CARDS);
+    let times = parse_times(matches);
+    let mut random = R::from_seed(seed);
+
+    if as_json {
+        let found = outs::find_outs(hole_cards, community_cards, times, &mut random);
+        let input = json::OutsInput {
+            hole_cards,
+            community_cards,
+            iterations: times,
+            rng: matches.value_of("rng").unwrap_or("lcg"),
+            seed,
+        };
+        print_json(&json::outs(&input, &found));
+    } else {
+        outs::run_outs(hole_cards, community_cards, times, seed, &mut random);
+    }
+}
+
+fn run_rngtest<R: Rng + FromSeed>(matches: &ArgMatches, seed: u64, as_json: bool) -> bool {
+    let times = parse_times(matches);
+    let max_z = rngtest::DEFAULT_MAX_Z_SCORE;
+    let results = rngtest::test_rng::<R>(seed, times);
+    if as_json {
+        let rng = matches.value_of("rng").unwrap_or("lcg");
+        print_json(&json::rngtest(&results, rng, seed, times, max_z));
+        results.iter().all(|result| result.passed(max_z))
+    } else {
+        rngtest::print_results(&results, seed, times, max_z)
+    }
+}
+
 fn main() {
-    use card::Card;
-    use hand::Hand;
-    use random::Random;
+    use theb::random::new_seed;
 
     let matches = App::new("Texas Hold 'Em Buddy")
         .version("1.0")
         .author("Atul Varma <varmaa@gmail.com>")
         .about("A tool for analyzing Texas Hold 'Em games")
         .after_help(AFTER_HELP)
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("N")
+                .help("Seed for the random number generator, to make runs reproducible")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("Output format. The README describes the JSON schema.")
+                .takes_value(true)
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("besthand")
                 .about("Attempts to deduce the best hand from a list of cards")
@@ -60,6 +805,7 @@ fn main() {
                         .required(true)
                         .index(1),
                 )
+                .arg(rng_arg())
                 .arg(
                     Arg::with_name("times")
                         .short("t")
@@ -68,38 +814,397 @@ fn main() {
                         .default_value("100000")
                         .help("Number of times to simulate play")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("opponents")
+                        .long("opponents")
+                        .value_name("N")
+                        .default_value("1")
+                        .help("Number of opponents to play against, from 1 to 9. The extra reports always play against one.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("variance")
+                        .long("variance")
+                        .help("Also estimates equity using variance reduction techniques and reports how much they reduce the variance"),
+                )
+                .arg(
+                    Arg::with_name("compare")
+                        .long("compare")
+                        .value_names(&["CARD", "CARD"])
+                        .help("Also compares our equity to that of these other hole cards, playing both on the same runouts")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("streets")
+                        .long("streets")
+                        .help("Also reports how our equity develops on each street, and how often we are ahead or behind along the way"),
+                )
+                .arg(
+                    Arg::with_name("pot")
+                        .long("pot")
+                        .value_name("CHIPS")
+                        .requires("to-call")
+                        .help("Also works out whether calling is profitable, given the chips in the pot including the bet we face")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to-call")
+                        .long("to-call")
+                        .value_name("CHIPS")
+                        .requires("pot")
+                        .help("The chips we need to put in to call")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("stack")
+                        .long("stack")
+                        .value_name("CHIPS")
+                        .requires("pot")
+                        .help("The chips we have left. If it doesn't cover the bet, calling puts us all in for less.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("future-bets")
+                        .long("future-bets")
+                        .value_name("FRACTION")
+                        .requires("pot")
+                        .help("Also estimates implied odds, assuming each player bets and calls this fraction of the pot on every later street whenever both end up with a pair or better")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("big-blind")
+                        .long("big-blind")
+                        .value_name("CHIPS")
+                        .default_value("1")
+                        .help("The size of the big blind, to also show amounts in big blinds")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("live")
+                        .long("live")
+                        .help("Shows the equity estimate so far and its confidence interval while simulating. Progress is shown anyway when stderr is a terminal."),
+                )
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .value_name("MS")
+                        .default_value("500")
+                        .help("Milliseconds between updates of the progress shown while simulating")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dump")
+                        .long("dump")
+                        .value_name("FILE")
+                        .help("Also writes every simulated deal to this file, as JSON lines if it ends in '.jsonl' and as CSV otherwise. With more than one thread, deals are written in no particular order.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .default_value("1")
+                        .help("Number of threads to simulate play on, or 0 to use every CPU. Results are only reproducible for the same seed and number of threads.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("icm")
+                .about("Values tournament stacks in prize money, and decides whether to get all in")
+                .arg(
+                    Arg::with_name("stacks")
+                        .long("stacks")
+                        .value_name("CHIPS")
+                        .required(true)
+                        .min_values(2)
+                        .use_delimiter(true)
+                        .help("Every player's stack, e.g. '5000 3000 2000'. Players are numbered from 1 in this order.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("payouts")
+                        .long("payouts")
+                        .value_name("PRIZE")
+                        .required(true)
+                        .min_values(1)
+                        .use_delimiter(true)
+                        .help("The prize for each place, from first down, e.g. '50 30 20'")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("hero")
+                        .long("hero")
+                        .value_name("PLAYER")
+                        .requires("villain")
+                        .help("Also decides whether this player should get all in against --villain")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("villain")
+                        .long("villain")
+                        .value_name("PLAYER")
+                        .requires("hero")
+                        .help("The player we'd be all in against")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("pot")
+                        .long("pot")
+                        .value_name("CHIPS")
+                        .default_value("0")
+                        .help("Blinds and antes already in the pot, which aren't part of the stacks")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("equity")
+                        .long("equity")
+                        .value_name("FRACTION")
+                        .conflicts_with("CARD")
+                        .help("Our equity when all in, from 0 to 1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("CARD")
+                        .help("Our hole cards, e.g. 'as ks', to work out our equity against a random hand instead of giving --equity. It's looked up in the preflop table if there is one, and simulated otherwise.")
+                        .number_of_values(texas::NUM_HOLE_CARDS as u64)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("fold-chance")
+                        .long("fold-chance")
+                        .value_name("FRACTION")
+                        .help("How often the villain folds if we shove, to also decide whether to shove")
+                        .takes_value(true),
+                )
+                .arg(rng_arg())
+                .arg(table_arg())
+                .arg(
+                    Arg::with_name("times")
+                        .short("t")
+                        .long("times")
+                        .value_name("N")
+                        .default_value("100000")
+                        .help("Number of times to simulate play to estimate our equity")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("outs")
+                .about("Lists the unseen cards that would improve our hand on the flop or turn")
+                .arg(
+                    Arg::with_name("CARD")
+                        .help("A card, e.g. 'qs' for Queen of Spades, '2c' for Two of Clubs. The first two are hole cards, followed by the three or four community cards of the flop or turn.")
+                        .min_values(texas::NUM_HOLE_CARDS as u64 + 3)
+                        .max_values(texas::NUM_HOLE_CARDS as u64 + 4)
+                        .required(true)
+                        .index(1),
+                )
+                .arg(rng_arg())
+                .arg(
+                    Arg::with_name("times")
+                        .short("t")
+                        .long("times")
+                        .value_name("N")
+                        .default_value("10000")
+                        .help("Number of times to simulate play for each out")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("pushfold")
+                .about("Finds the equilibrium ranges to shove and call with when the small blind can only go all in or fold")
+                .arg(
+                    Arg::with_name("stack")
+                        .long("stack")
+                        .value_name("BB")
+                        .required(true)
+                        .help("The effective stack in big blinds, e.g. '10bb'")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("ante")
+                        .long("ante")
+                        .value_name("BB")
+                        .default_value("0")
+                        .help("The ante each player posts, in big blinds")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("players")
+                        .long("players")
+                        .value_name("N")
+                        .default_value("2")
+                        .help("How many players were dealt in. Everyone but the blinds has folded, but their antes are in the pot.")
+                        .takes_value(true),
+                )
+                .arg(rng_arg())
+                .arg(table_arg())
+                .arg(
+                    Arg::with_name("times")
+                        .short("t")
+                        .long("times")
+                        .value_name("N")
+                        .default_value("2000")
+                        .help("Number of games to simulate for each pair of starting hands, when there's no preflop table")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .value_name("N")
+                        .default_value("0")
+                        .help("Number of threads to simulate matchups on, or 0 to use every CPU. Results are only reproducible for the same seed and number of threads.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("precompute")
+                .about("Precomputes tables that other commands load to skip simulating")
+                .subcommand(
+                    SubCommand::with_name("preflop")
+                        .about("Works out the all-in equity of every starting hand against every other, heads up")
+                        .arg(
+                            Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .value_name("FILE")
+                                .default_value(DEFAULT_PREFLOP_TABLE)
+                                .help("Where to write the table")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::with_name("threads")
+                                .long("threads")
+                                .value_name("N")
+                                .default_value("0")
+                                .help("Number of threads to work on, or 0 to use every CPU. Simulated results are only reproducible for the same seed and number of threads.")
+                                .takes_value(true),
+                        )
+                        .arg(rng_arg())
+                        .arg(
+                            Arg::with_name("times")
+                                .short("t")
+                                .long("times")
+                                .value_name("N")
+                                .help("Estimate each matchup from this many simulated games instead of dealing every board")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("strength")
+                .about("Ranks our hand against every two cards an opponent could hold on the flop, turn or river")
+                .arg(
+                    Arg::with_name("CARD")
+                        .help("A card, e.g. 'qs' for Queen of Spades, '2c' for Two of Clubs. The first two are hole cards, followed by the three to five community cards.")
+                        .min_values(texas::NUM_HOLE_CARDS as u64 + 3)
+                        .max_values(texas::NUM_TOTAL_CARDS as u64)
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rngtest")
+                .about("Runs statistical tests on the shuffling of a random number generator")
+                .arg(rng_arg())
+                .arg(
+                    Arg::with_name("times")
+                        .short("t")
+                        .long("times")
+                        .value_name("N")
+                        .default_value("100000")
+                        .help("Number of times to shuffle for each test")
+                        .takes_value(true),
                 ),
         )
         .subcommand(SubCommand::with_name("test").about("Runs a manual test"))
         .get_matches();
 
+    let seed = if matches.is_present("seed") {
+        value_t!(matches.value_of("seed"), u64).unwrap_or_else(|e| e.exit())
+    } else {
+        new_seed()
+    };
+    let as_json = matches.value_of("format") == Some("json");
+
     if let Some(matches) = matches.subcommand_matches("besthand") {
-        let cards = values_t!(matches.values_of("CARD"), Card).unwrap_or_else(|e| e.exit());
-        let hand = Hand::from(cards);
+        let cards = parse_cards(matches);
+        let hand = Hand::from(cards.clone());
         let cat = hand
             .find_best_category()
             .expect("clap ensures we have a non-empty hand");
-        let kickers = cat.get_kickers(&hand);
-        println!("The best hand for\n  {}\nis\n  {:?}", hand, cat);
-        if kickers.is_empty() {
-            println!("with no kickers.");
-        } else {
-            println!("with kickers\n  {}.", Card::vec_to_string(&kickers));
+        if as_json {
+            print_json(&json::besthand(&cards, &hand, &cat));
+            return;
         }
+        println!(
+            "The best hand for\n  {}\nis\n  {}.",
+            hand,
+            cat.describe(&hand)
+        );
     } else if let Some(matches) = matches.subcommand_matches("play") {
-        let cards = values_t!(matches.values_of("CARD"), Card).unwrap_or_else(|e| e.exit());
-        let hole_cards = cards[0..texas::NUM_HOLE_CARDS].to_owned();
-        let community_cards = cards[texas::NUM_HOLE_CARDS..].to_owned();
-        let times = value_t!(matches.value_of("times"), usize).unwrap_or_else(|e| e.exit());
-
-        texas::run_texas_hold_em(hole_cards, community_cards, times, Random::new());
-    } else if let Some(_) = matches.subcommand_matches("test") {
-        let mut r = Random::new();
+        match matches.value_of("rng") {
+            Some("xorshift") => play::<Xorshift>(matches, seed, as_json),
+            _ => play::<Random>(matches, seed, as_json),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("outs") {
+        match matches.value_of("rng") {
+            Some("xorshift") => run_outs::<Xorshift>(matches, seed, as_json),
+            _ => run_outs::<Random>(matches, seed, as_json),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("icm") {
+        match matches.value_of("rng") {
+            Some("xorshift") => run_icm::<Xorshift>(matches, seed, as_json),
+            _ => run_icm::<Random>(matches, seed, as_json),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("pushfold") {
+        match matches.value_of("rng") {
+            Some("xorshift") => run_pushfold::<Xorshift>(matches, seed, as_json),
+            _ => run_pushfold::<Random>(matches, seed, as_json),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("precompute") {
+        if let Some(matches) = matches.subcommand_matches("preflop") {
+            match matches.value_of("rng") {
+                Some("xorshift") => run_precompute_preflop::<Xorshift>(matches, seed, as_json),
+                _ => run_precompute_preflop::<Random>(matches, seed, as_json),
+            }
+        } else {
+            println!("Please specify a table to precompute. Run 'theb help precompute' for more details.");
+            std::process::exit(1);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("strength") {
+        let cards = parse_cards(matches);
+        let (hole_cards, community_cards) = cards.split_at(texas::NUM_HOLE_CARDS);
+        let found = strength::hand_strength(hole_cards, community_cards);
+        if as_json {
+            print_json(&json::strength(hole_cards, community_cards, &found));
+        } else {
+            strength::print_strength(hole_cards, community_cards, &found);
+        }
+    } else if let Some(matches) = matches.subcommand_matches("rngtest") {
+        let passed = match matches.value_of("rng") {
+            Some("xorshift") => run_rngtest::<Xorshift>(matches, seed, as_json),
+            _ => run_rngtest::<Random>(matches, seed, as_json),
+        };
+        if !passed {
+            std::process::exit(1);
+        }
+    } else if matches.subcommand_matches("test").is_some() {
+        let mut r = Random::from_seed(seed);
         let mut deck = Card::new_deck();
         r.shuffle(&mut deck);
         let hand = Hand::from(deck[0..7].to_owned());
+        let cat = hand
+            .find_best_category()
+            .expect("a seven-card hand is non-empty");
+        if as_json {
+            print_json(&json::test(seed, &hand, &cat));
+            return;
+        }
+        println!("Random seed:\n  {}", seed);
         println!("Here's a hand:\n  {}", hand);
-        println!("Its best category is:\n  {:?}", hand.find_best_category());
+        println!("Its best category is:\n  {}", cat.describe(&hand));
     } else {
         println!("Please specify a subcommand. Run 'theb help' for more details.");
         std::process::exit(1);
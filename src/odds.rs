@@ -0,0 +1,453 @@
+//! Turns our equity into a decision about calling a bet, by comparing it
+//! with the price the pot is offering.
+// https://en.wikipedia.org/wiki/Pot_odds
+// https://en.wikipedia.org/wiki/Implied_odds
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::card::Value;
+use super::hand::Category;
+use super::progress::POT_SHARE_UNITS;
+use super::texas::Deal;
+use super::variance::Estimate;
+
+/// The bet we are facing. All amounts are in chips.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PotOdds {
+    /// Everything in the middle, including the bet we are facing.
+    pub pot: f64,
+    pub to_call: f64,
+    /// What we have left behind, if it might not cover the call.
+    pub stack: Option<f64>,
+    pub big_blind: f64,
+}
+
+impl PotOdds {
+    /// How much we actually put in, which is less than the bet if it puts
+    /// us all in.
+    pub fn call_amount(&self) -> f64 {
+        self.stack
+            .map_or(self.to_call, |stack| self.to_call.min(stack))
+    }
+
+    pub fn is_all_in(&self) -> bool {
+        self.stack.is_some_and(|stack| self.to_call >= stack)
+    }
+
+    /// What we win besides our own call. If we can't cover the bet, the
+    /// part of it we don't match goes back to the bettor.
+    pub fn pot_to_win(&self) -> f64 {
+        self.pot - (self.to_call - self.call_amount())
+    }
+
+    /// The share of the final pot we put in, which is the equity at which
+    /// calling breaks even.
+    pub fn required_equity(&self) -> f64 {
+        self.call_amount() / (self.pot_to_win() + self.call_amount())
+    }
+
+    /// The pot odds as "N to 1".
+    pub fn odds_against(&self) -> f64 {
+        self.pot_to_win() / self.call_amount()
+    }
+
+    /// What we have left behind after calling, compared with the pot then,
+    /// if we know our stack.
+    pub fn stack_to_pot(&self) -> Option<f64> {
+        self.stack
+            .map(|stack| (stack - self.call_amount()) / (self.pot_to_win() + self.call_amount()))
+    }
+
+    /// Our expected profit from calling rather than folding, in chips, for
+    /// the given share of the final pot.
+    pub fn call_ev(&self, equity: f64) -> f64 {
+        equity * (self.pot_to_win() + self.call_amount()) - self.call_amount()
+    }
+
+    pub fn in_big_blinds(&self, chips: f64) -> f64 {
+        chips / self.big_blind
+    }
+
+    /// How much each player puts in on the later streets if they bet the
+    /// given fraction of the pot on each one and get called, stopping once
+    /// we are all in.
+    pub fn future_bets(&self, fraction: f64, num_community_cards: usize) -> f64 {
+        let num_streets = match num_community_cards {
+            0 => 3,
+            3 => 2,
+            4 => 1,
+            _ => 0,
+        };
+        let pot = self.pot_to_win() + self.call_amount();
+        let bets = pot * ((1.0 + 2.0 * fraction).powi(num_streets) - 1.0) / 2.0;
+        match self.stack {
+            Some(stack) => bets.min(stack - self.call_amount()),
+            None => bets,
+        }
+    }
+}
+
+/// The weakest hand that bets on later streets, and the weakest that calls
+/// those bets. Weaker hands check down or fold.
+const WEAKEST_BETTING_HAND: Category = Category::TwoPair(Value::Three, Value::Two);
+const WEAKEST_CALLING_HAND: Category = Category::OnePair(Value::Two);
+
+/// Tallies, across simulated runouts, how the money bet on later streets
+/// would move between us and the best opponent. We assume the bets only go
+/// in when the stronger hand is at least two pair and the weaker one at
+/// least a pair, since otherwise nobody bets or somebody folds. Runouts are
+/// counted in the same units as `Progress`, and every simulation thread adds
+/// its own through a `LocalFutureBetTally`.
+#[derive(Default)]
+pub struct FutureBetTally {
+    games: AtomicUsize,
+    /// How much of the future bets we collect from the runouts we win.
+    gains: AtomicU64,
+    /// How much of the future bets we pay off on the runouts we lose.
+    losses: AtomicU64,
+}
+
+impl FutureBetTally {
+    /// Returns a tally of one simulation thread's runouts.
+    pub fn local(&self) -> LocalFutureBetTally<'_> {
+        LocalFutureBetTally {
+            tally: self,
+            games: 0,
+            gains: 0,
+            losses: 0,
+        }
+    }
+
+    /// Returns the implied odds of calling if each player puts in
+    /// `future_bets` later on whenever the hands are strong enough.
+    pub fn implied_odds(&self, future_bets: f64, made_hand: bool) -> ImpliedOdds {
+        let games = self.games.load(Ordering::Relaxed).max(1) as f64;
+        let rate = |total: &AtomicU64| {
+            total.load(Ordering::Relaxed) as f64 / POT_SHARE_UNITS as f64 / games
+        };
+        ImpliedOdds {
+            future_bets,
+            gain_rate: rate(&self.gains),
+            loss_rate: rate(&self.losses),
+            made_hand,
+        }
+    }
+}
+
+/// Tallies the runouts played on one thread, and adds them to its
+/// `FutureBetTally` when it's dropped.
+pub struct LocalFutureBetTally<'a> {
+    tally: &'a FutureBetTally,
+    games: usize,
+    gains: u64,
+    losses: u64,
+}
+
+impl LocalFutureBetTally<'_> {
+    pub fn record(&mut self, deal: &Deal) {
+        self.games += 1;
+        let weaker = deal.category.min(deal.opponent_category);
+        let stronger = deal.category.max(deal.opponent_category);
+        if stronger < WEAKEST_BETTING_HAND || weaker < WEAKEST_CALLING_HAND {
+            return;
+        }
+        // Of the future bets from both players, we get back our share of
+        // twice what we each put in, less our own.
+        let share = (deal.pot_share * POT_SHARE_UNITS as f64).round() as u64;
+        if 2 * share > POT_SHARE_UNITS {
+            self.gains += 2 * share - POT_SHARE_UNITS;
+        } else {
+            self.losses += POT_SHARE_UNITS - 2 * share;
+        }
+    }
+}
+
+impl Drop for LocalFutureBetTally<'_> {
+    fn drop(&mut self) {
+        let tally = self.tally;
+        tally.games.fetch_add(self.games, Ordering::Relaxed);
+        tally.gains.fetch_add(self.gains, Ordering::Relaxed);
+        tally.losses.fetch_add(self.losses, Ordering::Relaxed);
+    }
+}
+
+pub struct ImpliedOdds {
+    /// How much each player puts in on later streets when the hands are
+    /// strong enough to bet and call.
+    pub future_bets: f64,
+    /// The average share of `future_bets` we win, over all runouts.
+    pub gain_rate: f64,
+    /// The average share of `future_bets` we lose, over all runouts.
+    pub loss_rate: f64,
+    /// Whether we already have a pair or better.
+    pub made_hand: bool,
+}
+
+impl ImpliedOdds {
+    /// What we expect to win from the later bets on the runouts we win.
+    pub fn implied_gain(&self) -> f64 {
+        self.future_bets * self.gain_rate
+    }
+
+    /// What we expect to pay off on the runouts we lose.
+    pub fn reverse_implied_cost(&self) -> f64 {
+        self.future_bets * self.loss_rate
+    }
+
+    /// How much each player would have to put in later on for a call that
+    /// loses the given amount on pot odds alone to break even, if any
+    /// amount would do.
+    pub fn future_bets_needed(&self, pot_odds_ev: f64) -> Option<f64> {
+        let net_rate = self.gain_rate - self.loss_rate;
+        if pot_odds_ev >= 0.0 {
+            Some(0.0)
+        } else if net_rate > 0.0 {
+            Some(-pot_odds_ev / net_rate)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether calling is profitable given our equity.
+pub struct CallReport {
+    pub odds: PotOdds,
+    pub equity: Estimate,
+    /// Whether `equity` was counted exactly rather than simulated.
+    pub exact: bool,
+    pub implied: Option<ImpliedOdds>,
+}
+
+impl CallReport {
+    /// Our expected profit from calling in chips, along with its
+    /// uncertainty from that of our equity.
+    pub fn ev(&self) -> Estimate {
+        let final_pot = self.odds.pot_to_win() + self.odds.call_amount();
+        Estimate {
+            mean: self.odds.call_ev(self.equity.mean),
+            variance: self.equity.variance * final_pot * final_pot,
+            samples: self.equity.samples,
+        }
+    }
+
+    /// "Call" or "Fold" if the confidence interval of the EV is clear of
+    /// zero, and "Too close to call" otherwise.
+    pub fn decision(&self) -> &'static str {
+        let (low, high) = self.ev().confidence_interval();
+        if low > 0.0 {
+            "Call"
+        } else if high < 0.0 {
+            "Fold"
+        } else {
+            "Too close to call"
+        }
+    }
+
+    /// Our expected profit from calling once the later bets are counted.
+    pub fn implied_ev(&self) -> Option<f64> {
+        self.implied
+            .as_ref()
+            .map(|implied| self.ev().mean + implied.implied_gain() - implied.reverse_implied_cost())
+    }
+
+    /// Whether the call loses money on pot odds alone but makes money once
+    /// the later bets are counted.
+    pub fn profitable_on_implied_odds(&self) -> bool {
+        self.ev().mean < 0.0 && self.implied_ev().is_some_and(|ev| ev > 0.0)
+    }
+
+    /// Whether we have a made hand that pays off better hands later on more
+    /// than it gets paid by worse ones.
+    pub fn has_reverse_implied_odds(&self) -> bool {
+        self.implied.as_ref().is_some_and(|implied| {
+            implied.made_hand && implied.reverse_implied_cost() > implied.implied_gain()
+        })
+    }
+
+    pub fn print(&self) {
+        let odds = &self.odds;
+        let chips = |amount: f64| format!("{} ({} BB)", amount, odds.in_big_blinds(amount));
+
+        println!(
+            "Calling {} into a pot of {}{}:\n",
+            chips(odds.call_amount()),
+            chips(odds.pot),
+            if odds.is_all_in() { ", all in" } else { "" }
+        );
+        println!("  Pot odds:         {:.2} to 1", odds.odds_against());
+        println!("  Required equity:  {:.2}%", odds.required_equity() * 100.0);
+        println!(
+            "  Our equity:       {:.2}%{}",
+            self.equity.mean * 100.0,
+            if self.exact {
+                String::from(" (exact)")
+            } else {
+                format!(" ± {:.2}%", self.equity.std_error() * 100.0)
+            }
+        );
+        if let Some(stack_to_pot) = odds.stack_to_pot() {
+            println!("  Stack to pot:     {:.2}", stack_to_pot);
+        }
+
+        let ev = self.ev();
+        let (low, high) = ev.confidence_interval();
+        println!(
+            "  EV of calling:    {:+.2} chips ({:+.2} BB)",
+            ev.mean,
+            odds.in_big_blinds(ev.mean)
+        );
+        if !self.exact {
+            println!("  95% CI:           {:+.2} to {:+.2} chips", low, high);
+        }
+        println!("  Decision:         {}", self.decision());
+
+        if let (Some(implied), Some(implied_ev)) = (&self.implied, self.implied_ev()) {
+            println!();
+            println!(
+                "With each player putting in {:.2} chips on later streets when one has two pair or better and the other a pair or better:\n",
+                implied.future_bets
+            );
+            println!(
+                "  Implied odds:     {:+.2} chips from the runouts we win",
+                implied.implied_gain()
+            );
+            println!(
+                "  Reverse implied:  {:+.2} chips on the runouts we lose",
+                -implied.reverse_implied_cost()
+            );
+            println!(
+                "  EV of calling:    {:+.2} chips ({:+.2} BB)",
+                implied_ev,
+                odds.in_big_blinds(implied_ev)
+            );
+            if ev.mean < 0.0 {
+                match implied.future_bets_needed(ev.mean) {
+                    Some(needed) => println!(
+                        "  Break-even:       each player must put in {:.2} chips later on",
+                        needed
+                    ),
+                    None => {
+                        println!("  Break-even:       no amount of later betting makes up for it")
+                    }
+                }
+            }
+            if self.profitable_on_implied_odds() {
+                println!("  Note:             Unprofitable on pot odds alone, but profitable on implied odds");
+            }
+            if self.has_reverse_implied_odds() {
+                println!("  Note:             Our made hand pays off better hands more than worse ones pay it");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::super::texas::simulate_with;
+    use super::super::variance::Estimate;
+    use super::{CallReport, FutureBetTally, PotOdds};
+
+    fn odds(pot: f64, to_call: f64, stack: Option<f64>) -> PotOdds {
+        PotOdds {
+            pot,
+            to_call,
+            stack,
+            big_blind: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_pot_odds_work() {
+        // Calling 50 into a pot of 150 risks 50 to win 150.
+        let odds = odds(150.0, 50.0, None);
+        assert_eq!(odds.odds_against(), 3.0);
+        assert_eq!(odds.required_equity(), 0.25);
+        assert_eq!(odds.call_ev(0.25), 0.0);
+        assert_eq!(odds.call_ev(0.5), 50.0);
+        assert_eq!(odds.in_big_blinds(50.0), 25.0);
+    }
+
+    #[test]
+    fn test_short_stack_only_calls_what_it_has() {
+        // Of the 100 bet we can only match 40, so 60 goes back.
+        let odds = odds(200.0, 100.0, Some(40.0));
+        assert!(odds.is_all_in());
+        assert_eq!(odds.call_amount(), 40.0);
+        assert_eq!(odds.pot_to_win(), 140.0);
+        assert_eq!(odds.required_equity(), 40.0 / 180.0);
+        assert_eq!(odds.stack_to_pot(), Some(0.0));
+    }
+
+    #[test]
+    fn test_decision_accounts_for_uncertainty() {
+        let report = |mean, variance| CallReport {
+            odds: odds(150.0, 50.0, None),
+            equity: Estimate {
+                mean,
+                variance,
+                samples: 100,
+            },
+            exact: false,
+            implied: None,
+        };
+        assert_eq!(report(0.4, 0.0001).decision(), "Call");
+        assert_eq!(report(0.1, 0.0001).decision(), "Fold");
+        assert_eq!(report(0.26, 0.01).decision(), "Too close to call");
+    }
+
+    #[test]
+    fn test_future_bets_compound_and_stop_at_our_stack() {
+        // A pot of 200 after calling, with half-pot bets on the turn and
+        // river: 100 each to make 400, then 200 each.
+        let on_the_flop = odds(150.0, 50.0, None);
+        assert_eq!(on_the_flop.future_bets(0.5, 3), 300.0);
+        assert_eq!(on_the_flop.future_bets(0.5, 5), 0.0);
+        let short = odds(150.0, 50.0, Some(150.0));
+        assert_eq!(short.future_bets(0.5, 3), 100.0);
+    }
+
+    fn implied_report(hole_cards: &str, board: &str, odds: PotOdds, made_hand: bool) -> CallReport {
+        let hole_cards = Card::try_vec_from(hole_cards).unwrap();
+        let board = Card::try_vec_from(board).unwrap();
+        let tally = FutureBetTally::default();
+        let mut local = tally.local();
+        let results = simulate_with(
+            &hole_cards,
+            &board,
+            1,
+            10000,
+            &mut Xorshift::from_seed(1),
+            |deal| {
+                local.record(deal);
+                ControlFlow::Continue(())
+            },
+        );
+        drop(local);
+        CallReport {
+            odds,
+            equity: results.equity(),
+            exact: false,
+            implied: Some(tally.implied_odds(odds.future_bets(1.0, board.len()), made_hand)),
+        }
+    }
+
+    #[test]
+    fn test_drawing_hand_can_call_on_implied_odds() {
+        // Calling 80 into 100 needs 44% of the pot, which a flush and
+        // straight draw doesn't quite have, but it gets paid when it hits.
+        let report = implied_report("6s 5s", "7s 8d 2s kc", odds(100.0, 80.0, None), false);
+        assert!(report.ev().mean < 0.0);
+        assert!(report.profitable_on_implied_odds());
+        assert!(!report.has_reverse_implied_odds());
+    }
+
+    #[test]
+    fn test_small_pair_has_reverse_implied_odds() {
+        let report = implied_report("3d 3c", "kc 9s 8s jh", odds(100.0, 50.0, None), true);
+        assert!(report.has_reverse_implied_odds());
+        assert!(!report.profitable_on_implied_odds());
+    }
+}
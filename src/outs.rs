@@ -0,0 +1,216 @@
+// https://en.wikipedia.org/wiki/Out_(poker)
+use super::card::Card;
+use super::combinations::Combinations;
+use super::hand::{Category, Hand};
+use super::random::Rng;
+use super::texas::{remove_from_deck, simulate, NUM_HOLE_CARDS, TIE, WIN};
+
+/// The largest share of opponent holdings an out can carry past us while
+/// still counting as clean.
+pub const MAX_CLEAN_OPPONENTS_HELPED: f64 = 0.05;
+
+/// An unseen card that improves our hand.
+pub struct Out {
+    pub card: Card,
+    /// Our best category once the card is dealt.
+    pub category: Category,
+    pub description: String,
+    /// How often we beat a random opponent once the card is dealt.
+    pub win_rate: f64,
+    pub tie_rate: f64,
+    /// The share of opponent holdings that weren't ahead of us before the
+    /// card was dealt, but are after it.
+    pub opponents_helped: f64,
+}
+
+impl Out {
+    /// Our share of the pot against a random opponent, counting a tie as
+    /// half a win.
+    pub fn equity(&self) -> f64 {
+        self.win_rate + self.tie_rate / 2.0
+    }
+
+    /// Whether the card makes us a favourite without also carrying many
+    /// opponent holdings past us.
+    pub fn is_clean(&self) -> bool {
+        self.equity() > 0.5 && self.opponents_helped <= MAX_CLEAN_OPPONENTS_HELPED
+    }
+}
+
+fn category_rank(cards: &[Card]) -> usize {
+    Hand::from(cards.to_vec())
+        .find_best_category()
+        .map_or(0, |category| category.rank())
+}
+
+/// Finds every unseen card that improves our hand on the given flop or turn.
+/// A card only counts as improving our hand if it raises our category by more
+/// than it raises the board's own, so pairing the board isn't an out. Each
+/// out's win rate is estimated by simulating the given number of games
+/// against a random opponent.
+pub fn find_outs<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_iterations: usize,
+    random: &mut R,
+) -> Vec<Out> {
+    assert_eq!(hole_cards.len(), NUM_HOLE_CARDS, "Must have 2 hole cards");
+    assert!(
+        board.len() == 3 || board.len() == 4,
+        "Must have a flop or a turn"
+    );
+
+    let deck = remove_from_deck(Card::new_deck(), [board, hole_cards].concat());
+    let hand = Hand::from([board, hole_cards].concat());
+    let rank = category_rank(&[board, hole_cards].concat());
+    let board_rank = category_rank(board);
+
+    let holdings = Combinations::new(&deck, NUM_HOLE_CARDS)
+        .map(|holding| {
+            let ahead = Hand::from([board, &holding].concat()) > hand;
+            (holding, ahead)
+        })
+        .collect::<Vec<_>>();
+
+    let mut outs = vec![];
+    for &card in deck.iter() {
+        let new_board = [board, &[card]].concat();
+        let new_hand = Hand::from([&new_board, hole_cards].concat());
+        let category = new_hand
+            .find_best_category()
+            .expect("a hand with hole cards is non-empty");
+        if category.rank() - rank <= category_rank(&new_board) - board_rank {
+            continue;
+        }
+
+        let mut num_holdings = 0;
+        let mut num_helped = 0;
+        for (holding, ahead) in holdings.iter() {
+            if holding.contains(&card) {
+                continue;
+            }
+            num_holdings += 1;
+            if !ahead && Hand::from([&new_board, holding.as_slice()].concat()) > new_hand {
+                num_helped += 1;
+            }
+        }
+
+        let results = simulate(hole_cards, &new_board, num_iterations, random);
+        let rate = |outcome| {
+            results.game_outcomes.get(outcome).copied().unwrap_or(0) as f64 / num_iterations as f64
+        };
+
+        outs.push(Out {
+            card,
+            category,
+            description: category.describe(&new_hand),
+            win_rate: rate(WIN),
+            tie_rate: rate(TIE),
+            opponents_helped: num_helped as f64 / num_holdings as f64,
+        });
+    }
+
+    // Show the strongest outs first.
+    outs.sort_by(|a, b| {
+        b.equity()
+            .total_cmp(&a.equity())
+            .then(b.card.value.cmp(&a.card.value))
+            .then(b.card.suit.cmp(&a.card.suit))
+    });
+    outs
+}
+
+fn print_outs(outs: &[&Out]) {
+    println!(
+        "  {:4} {:44} {:>7} {:>7} {:>8}",
+        "Card", "Hand", "Win", "Tie", "Helped"
+    );
+    for out in outs.iter() {
+        println!(
+            "  {:4} {:44} {:>6.1}% {:>6.1}% {:>7.1}%",
+            out.card.short_name(),
+            out.description,
+            out.win_rate * 100.0,
+            out.tie_rate * 100.0,
+            out.opponents_helped * 100.0
+        );
+    }
+}
+
+pub fn run_outs<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_iterations: usize,
+    seed: u64,
+    random: &mut R,
+) {
+    println!("Hole cards:\n  {}", Card::vec_to_string(hole_cards));
+    println!("Community cards:\n  {}", Card::vec_to_string(board));
+    let hand = Hand::from([board, hole_cards].concat());
+    let category = hand
+        .find_best_category()
+        .expect("a hand with hole cards is non-empty");
+    println!("Current hand:\n  {}", category.describe(&hand));
+    println!("Random seed:\n  {}", seed);
+    println!();
+
+    let outs = find_outs(hole_cards, board, num_iterations, random);
+    let (clean, tainted): (Vec<&Out>, Vec<&Out>) = outs.iter().partition(|out| out.is_clean());
+    let num_unseen = remove_from_deck(Card::new_deck(), [board, hole_cards].concat()).len();
+
+    println!(
+        "{} of {} unseen cards improve our hand. Win rates are against one random opponent over {} games each; 'Helped' is the share of opponent holdings the card carries past us.",
+        outs.len(),
+        num_unseen,
+        num_iterations
+    );
+    println!();
+
+    println!(
+        "Clean outs ({}), which make us a favourite and help at most {:.0}% of opponent holdings:\n",
+        clean.len(),
+        MAX_CLEAN_OPPONENTS_HELPED * 100.0
+    );
+    print_outs(&clean);
+    println!();
+
+    println!(
+        "Tainted outs ({}), which also help likely opponent holdings or leave us an underdog:\n",
+        tainted.len()
+    );
+    print_outs(&tainted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::find_outs;
+
+    fn outs(hole_cards: &str, board: &str) -> Vec<String> {
+        let hole_cards = Card::try_vec_from(hole_cards).unwrap();
+        let board = Card::try_vec_from(board).unwrap();
+        let mut outs = find_outs(&hole_cards, &board, 100, &mut Xorshift::from_seed(1))
+            .iter()
+            .map(|out| out.card.short_name())
+            .collect::<Vec<_>>();
+        outs.sort();
+        outs
+    }
+
+    #[test]
+    fn test_flush_draw_outs() {
+        let found = outs("as ks", "7s 2s 9d 4c");
+        // Nine spades, plus three aces and three kings to pair our hole cards.
+        assert_eq!(found.len(), 15);
+        assert!(found.contains(&String::from("Qs")));
+        assert!(found.contains(&String::from("Ah")));
+    }
+
+    #[test]
+    fn test_pairing_the_board_is_not_an_out() {
+        let found = outs("as ks", "7s 2h 9d 4c");
+        assert!(!found.contains(&String::from("7h")));
+        assert!(!found.contains(&String::from("2c")));
+    }
+}
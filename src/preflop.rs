@@ -0,0 +1,1062 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+
+use super::card::{Card, Value, ALL_SUITS, ALL_VALUES};
+use super::combinations::Combinations;
+use super::hand::Hand;
+use super::random::{derive_seed, FromSeed, Rng};
+use super::texas::{remove_from_deck, NUM_COMMUNITY_CARDS};
+
+/// How many strategically distinct starting hands there are.
+pub const NUM_STARTING_HANDS: usize = 169;
+
+/// A pair of hole cards, reduced to one of the 169 strategically distinct
+/// starting hands (e.g. "AKs", "T9o" or "77").
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StartingHand {
+    pub high: Value,
+    pub low: Value,
+    pub suited: bool,
+}
+
+/// The Sklansky-Malmuth starting hand groups, from strongest (group 1) to
+/// weakest (group 8). Hands not listed here fall outside of every group.
+const SKLANSKY_GROUPS: [&str; 8] = [
+    "AA KK QQ JJ AKs",
+    "TT AQs AJs KQs AKo",
+    "99 ATs KJs QJs JTs AQo",
+    "88 KTs QTs J9s T9s 98s AJo KQo",
+    "77 A9s A8s A7s A6s A5s A4s A3s A2s Q9s T8s 97s 87s 76s 65s KJo QJo JTo",
+    "66 55 K9s J8s 86s 75s 54s ATo KTo QTo",
+    "44 33 22 K8s K7s K6s K5s K4s K3s K2s Q8s T7s 64s 53s 43s J9o T9o 98o",
+    "J7s 96s 85s 74s 42s 32s A9o K9o Q9o J8o T8o 87o 76o 65o 54o",
+];
+
+/// Every starting hand's all-in equity against a single random hand, ordered
+/// from best to worst. These were computed by simulating 3,000,000 deals per
+/// starting hand.
+const EQUITY_VS_RANDOM: [(&str, f64); 169] = [
+    ("AA", 0.8522),
+    ("KK", 0.8239),
+    ("QQ", 0.7990),
+    ("JJ", 0.7747),
+    ("TT", 0.7498),
+    ("99", 0.7203),
+    ("88", 0.6916),
+    ("AKs", 0.6704),
+    ("77", 0.6630),
+    ("AQs", 0.6627),
+    ("AJs", 0.6541),
+    ("AKo", 0.6526),
+    ("ATs", 0.6463),
+    ("AQo", 0.6441),
+    ("AJo", 0.6359),
+    ("KQs", 0.6336),
+    ("66", 0.6333),
+    ("A9s", 0.6280),
+    ("ATo", 0.6277),
+    ("KJs", 0.6257),
+    ("A8s", 0.6191),
+    ("KTs", 0.6180),
+    ("KQo", 0.6146),
+    ("A7s", 0.6099),
+    ("A9o", 0.6077),
+    ("KJo", 0.6060),
+    ("55", 0.6033),
+    ("QJs", 0.6022),
+    ("K9s", 0.5994),
+    ("A8o", 0.5991),
+    ("A5s", 0.5990),
+    ("A6s", 0.5981),
+    ("KTo", 0.5972),
+    ("QTs", 0.5949),
+    ("A4s", 0.5906),
+    ("A7o", 0.5881),
+    ("K8s", 0.5831),
+    ("A3s", 0.5822),
+    ("QJo", 0.5817),
+    ("K9o", 0.5781),
+    ("A5o", 0.5771),
+    ("A6o", 0.5771),
+    ("Q9s", 0.5763),
+    ("K7s", 0.5756),
+    ("JTs", 0.5753),
+    ("A2s", 0.5738),
+    ("QTo", 0.5729),
+    ("44", 0.5705),
+    ("A4o", 0.5673),
+    ("K6s", 0.5661),
+    ("K8o", 0.5608),
+    ("Q8s", 0.5605),
+    ("A3o", 0.5585),
+    ("K5s", 0.5577),
+    ("J9s", 0.5568),
+    ("Q9o", 0.5536),
+    ("JTo", 0.5527),
+    ("K7o", 0.5518),
+    ("A2o", 0.5491),
+    ("K4s", 0.5490),
+    ("K6o", 0.5429),
+    ("Q7s", 0.5429),
+    ("T9s", 0.5406),
+    ("K3s", 0.5405),
+    ("J8s", 0.5403),
+    ("33", 0.5372),
+    ("Q6s", 0.5364),
+    ("Q8o", 0.5356),
+    ("K5o", 0.5329),
+    ("K2s", 0.5326),
+    ("J9o", 0.5324),
+    ("Q5s", 0.5275),
+    ("K4o", 0.5234),
+    ("J7s", 0.5232),
+    ("T8s", 0.5231),
+    ("Q4s", 0.5190),
+    ("Q7o", 0.5177),
+    ("T9o", 0.5162),
+    ("J8o", 0.5154),
+    ("K3o", 0.5138),
+    ("Q6o", 0.5102),
+    ("Q3s", 0.5101),
+    ("98s", 0.5076),
+    ("J6s", 0.5066),
+    ("T7s", 0.5062),
+    ("K2o", 0.5050),
+    ("22", 0.5037),
+    ("Q2s", 0.5016),
+    ("Q5o", 0.5016),
+    ("J5s", 0.5001),
+    ("T8o", 0.4975),
+    ("J7o", 0.4969),
+    ("97s", 0.4915),
+    ("Q4o", 0.4910),
+    ("J4s", 0.4906),
+    ("T6s", 0.4892),
+    ("J3s", 0.4821),
+    ("Q3o", 0.4820),
+    ("98o", 0.4812),
+    ("87s", 0.4795),
+    ("T7o", 0.4794),
+    ("J6o", 0.4786),
+    ("J2s", 0.4744),
+    ("96s", 0.4741),
+    ("Q2o", 0.4729),
+    ("T5s", 0.4725),
+    ("J5o", 0.4721),
+    ("T4s", 0.4654),
+    ("97o", 0.4627),
+    ("86s", 0.4622),
+    ("J4o", 0.4618),
+    ("T6o", 0.4612),
+    ("95s", 0.4571),
+    ("T3s", 0.4570),
+    ("76s", 0.4538),
+    ("J3o", 0.4532),
+    ("87o", 0.4507),
+    ("T2s", 0.4488),
+    ("96o", 0.4455),
+    ("85s", 0.4454),
+    ("J2o", 0.4432),
+    ("T5o", 0.4422),
+    ("94s", 0.4381),
+    ("75s", 0.4362),
+    ("T4o", 0.4351),
+    ("93s", 0.4329),
+    ("86o", 0.4325),
+    ("65s", 0.4316),
+    ("95o", 0.4271),
+    ("84s", 0.4267),
+    ("T3o", 0.4257),
+    ("92s", 0.4238),
+    ("76o", 0.4228),
+    ("74s", 0.4190),
+    ("T2o", 0.4165),
+    ("54s", 0.4151),
+    ("85o", 0.4143),
+    ("64s", 0.4136),
+    ("83s", 0.4089),
+    ("94o", 0.4064),
+    ("75o", 0.4049),
+    ("82s", 0.4025),
+    ("73s", 0.4008),
+    ("93o", 0.3999),
+    ("65o", 0.3997),
+    ("53s", 0.3971),
+    ("63s", 0.3954),
+    ("84o", 0.3943),
+    ("92o", 0.3914),
+    ("43s", 0.3871),
+    ("74o", 0.3851),
+    ("54o", 0.3818),
+    ("72s", 0.3810),
+    ("64o", 0.3802),
+    ("52s", 0.3786),
+    ("62s", 0.3766),
+    ("83o", 0.3748),
+    ("42s", 0.3684),
+    ("82o", 0.3683),
+    ("73o", 0.3660),
+    ("53o", 0.3633),
+    ("63o", 0.3608),
+    ("32s", 0.3598),
+    ("43o", 0.3516),
+    ("72o", 0.3459),
+    ("52o", 0.3426),
+    ("62o", 0.3410),
+    ("42o", 0.3319),
+    ("32o", 0.3230),
+];
+
+impl StartingHand {
+    pub fn new(first: Card, second: Card) -> Self {
+        let (high, low) = if first.value >= second.value {
+            (first.value, second.value)
+        } else {
+            (second.value, first.value)
+        };
+        StartingHand {
+            high,
+            low,
+            suited: high != low && first.suit == second.suit,
+        }
+    }
+
+    /// Returns every starting hand in the order of the usual 13×13 grid: row
+    /// by row from Aces down to Twos, with suited hands above the diagonal of
+    /// pairs and offsuit hands below it.
+    pub fn all() -> Vec<Self> {
+        (0..NUM_STARTING_HANDS)
+            .map(StartingHand::from_index)
+            .collect()
+    }
+
+    /// Returns the hand at the given position in `all()`.
+    pub fn from_index(index: usize) -> Self {
+        assert!(index < NUM_STARTING_HANDS, "No such starting hand");
+        let (row, column) = (index / ALL_VALUES.len(), index % ALL_VALUES.len());
+        let value = |position: usize| ALL_VALUES[ALL_VALUES.len() - 1 - position];
+        StartingHand {
+            high: value(row.min(column)),
+            low: value(row.max(column)),
+            suited: column > row,
+        }
+    }
+
+    /// Returns the hand's position in `all()`.
+    pub fn index(&self) -> usize {
+        let position = |value: Value| {
+            ALL_VALUES.len()
+                - 1
+                - ALL_VALUES
+                    .iter()
+                    .position(|&other| other == value)
+                    .expect("every value is in ALL_VALUES")
+        };
+        let (high, low) = (position(self.high), position(self.low));
+        if self.suited {
+            high * ALL_VALUES.len() + low
+        } else {
+            low * ALL_VALUES.len() + high
+        }
+    }
+
+    /// Returns every pair of hole cards that makes this hand: 6 for a pair,
+    /// 4 if suited and 12 if offsuit.
+    pub fn combos(&self) -> Vec<[Card; 2]> {
+        let mut result = vec![];
+        for (i, &high_suit) in ALL_SUITS.iter().enumerate() {
+            for (j, &low_suit) in ALL_SUITS.iter().enumerate() {
+                let is_combo = if self.is_pair() {
+                    i < j
+                } else if self.suited {
+                    i == j
+                } else {
+                    i != j
+                };
+                if is_combo {
+                    result.push([
+                        Card::new(self.high, high_suit),
+                        Card::new(self.low, low_suit),
+                    ]);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns how many combos of this hand are still possible once a combo
+    /// of `other` has been dealt. It's the same for every combo of `other`.
+    pub fn num_combos_given(&self, other: StartingHand) -> usize {
+        let dealt = other.combos()[0];
+        self.combos()
+            .iter()
+            .filter(|combo| !combo.iter().any(|card| dealt.contains(card)))
+            .count()
+    }
+
+    pub fn is_pair(&self) -> bool {
+        self.high == self.low
+    }
+
+    /// Returns the conventional label for the hand, e.g. "AKs", "T9o" or "77".
+    pub fn label(&self) -> String {
+        let mut result = format!("{}{}", self.high.to_char(), self.low.to_char());
+        if !self.is_pair() {
+            result.push(if self.suited { 's' } else { 'o' });
+        }
+        result
+    }
+
+    /// Returns the hand's Sklansky-Malmuth group (1 through 8), or `None` if
+    /// the hand doesn't belong to any group.
+    pub fn sklansky_group(&self) -> Option<u8> {
+        let label = self.label();
+        SKLANSKY_GROUPS
+            .iter()
+            .position(|group| group.split(' ').any(|hand| hand == label))
+            .map(|i| i as u8 + 1)
+    }
+
+    /// Scores the hand using Bill Chen's formula, as described in
+    /// https://en.wikipedia.org/wiki/Texas_hold_%27em_starting_hands#Chen_formula.
+    pub fn chen_score(&self) -> i8 {
+        // We work in half-points to avoid floating-point arithmetic.
+        let mut half_points: i8 = match self.high {
+            Value::Ace => 20,
+            Value::King => 16,
+            Value::Queen => 14,
+            Value::Jack => 12,
+            value => u8::from(&value) as i8,
+        };
+
+        if self.is_pair() {
+            return (half_points * 2).max(10) / 2;
+        }
+
+        if self.suited {
+            half_points += 4;
+        }
+
+        let gap = u8::from(&self.high) - u8::from(&self.low) - 1;
+        half_points -= match gap {
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            3 => 8,
+            _ => 10,
+        };
+
+        if gap <= 1 && self.high < Value::Queen {
+            half_points += 2;
+        }
+
+        // Round half points up.
+        if half_points >= 0 {
+            (half_points + 1) / 2
+        } else {
+            half_points / 2
+        }
+    }
+
+    /// Returns the hand's all-in equity against a single random hand, from
+    /// 0.0 to 1.0.
+    pub fn equity_vs_random(&self) -> f64 {
+        EQUITY_VS_RANDOM[self.equity_rank() - 1].1
+    }
+
+    /// Returns the hand's position (1 through 169) when all starting hands
+    /// are ordered by their equity against a single random hand.
+    pub fn equity_rank(&self) -> usize {
+        let label = self.label();
+        EQUITY_VS_RANDOM
+            .iter()
+            .position(|(hand, _)| *hand == label)
+            .expect("All starting hands should have a precomputed equity")
+            + 1
+    }
+}
+
+impl fmt::Display for StartingHand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl std::str::FromStr for StartingHand {
+    type Err = &'static str;
+
+    /// Parses a label like "AKs", "T9o" or "77".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() || s.len() < 2 || s.len() > 3 {
+            return Err("Starting hand must look like 'AKs', 'T9o' or '77'");
+        }
+
+        let first = s[0..1].parse::<Value>()?;
+        let second = s[1..2].parse::<Value>()?;
+        let (high, low) = if first >= second {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let suited = match (&s[2..], high == low) {
+            ("", true) => false,
+            ("s", false) | ("S", false) => true,
+            ("o", false) | ("O", false) => false,
+            _ => return Err("Starting hand must be a pair or end with 's' or 'o'"),
+        };
+
+        Ok(StartingHand { high, low, suited })
+    }
+}
+
+/// Prints a 13×13 grid of starting hands, in the order of
+/// `StartingHand::all()`, with each cell filled in by `cell`.
+pub fn print_grid<F: Fn(StartingHand) -> String>(cell: F) {
+    for row in StartingHand::all().chunks(ALL_VALUES.len()) {
+        let cells = row.iter().map(|&hand| format!("{:>4}", cell(hand)));
+        println!("  {}", cells.collect::<String>().trim_end());
+    }
+}
+
+/// How many matchups of two different starting hands there are.
+pub const NUM_MATCHUPS: usize = NUM_STARTING_HANDS * (NUM_STARTING_HANDS - 1) / 2;
+
+/// The bytes that start a file of precomputed equities, followed by the
+/// version of its layout. Bump the version whenever the layout changes.
+const TABLE_MAGIC: &[u8; 4] = b"THEB";
+const TABLE_VERSION: u16 = 1;
+
+/// The all-in equity of every starting hand against every other, heads up,
+/// counting a tie as half a win.
+pub struct HeadsUpEquities {
+    equities: Vec<f64>,
+    /// How many games each matchup was estimated from, or `None` if every
+    /// board was dealt.
+    pub games_per_matchup: Option<usize>,
+}
+
+impl HeadsUpEquities {
+    /// Builds the table from the equity of each hand against every hand
+    /// after it in `StartingHand::all()`, in that order. Mirrored matchups
+    /// are filled in from each other, and a hand against itself is an even
+    /// split.
+    fn from_matchups<I>(matchups: I, games_per_matchup: Option<usize>) -> Self
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        let mut equities = vec![0.5; NUM_STARTING_HANDS * NUM_STARTING_HANDS];
+        let mut matchups = matchups.into_iter();
+        for i in 0..NUM_STARTING_HANDS {
+            for j in (i + 1)..NUM_STARTING_HANDS {
+                let equity = matchups.next().expect("Every matchup has an equity");
+                equities[i * NUM_STARTING_HANDS + j] = equity;
+                equities[j * NUM_STARTING_HANDS + i] = 1.0 - equity;
+            }
+        }
+        HeadsUpEquities {
+            equities,
+            games_per_matchup,
+        }
+    }
+
+    /// Every matchup of two different starting hands, in the order the
+    /// table stores them.
+    fn matchups() -> Vec<(StartingHand, StartingHand)> {
+        let hands = StartingHand::all();
+        hands
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &hand)| hands[i + 1..].iter().map(move |&other| (hand, other)))
+            .collect()
+    }
+
+    /// Estimates every matchup by playing the given number of games, each
+    /// with randomly chosen combos of the two hands and a random board.
+    pub fn simulate<R: Rng>(games_per_matchup: usize, random: &mut R) -> Self {
+        let equities = Self::matchups()
+            .into_iter()
+            .map(|(hand, other)| simulate_matchup(hand, other, games_per_matchup, random))
+            .collect::<Vec<_>>();
+        Self::from_matchups(equities, Some(games_per_matchup))
+    }
+
+    /// Like `simulate()`, but spreads the matchups over the given number of
+    /// threads, each with its own generator derived from `seed`. Results
+    /// are only reproducible for the same seed and number of threads.
+    pub fn simulate_in_parallel<R, F>(
+        games_per_matchup: usize,
+        seed: u64,
+        num_threads: usize,
+        on_matchup: F,
+    ) -> Self
+    where
+        R: Rng + FromSeed,
+        F: Fn() + Sync,
+    {
+        let equities = in_parallel(
+            num_threads,
+            |thread| R::from_seed(derive_seed(seed, thread as u64)),
+            |random, hand, other| {
+                let equity = simulate_matchup(hand, other, games_per_matchup, random);
+                on_matchup();
+                equity
+            },
+        );
+        Self::from_matchups(equities, Some(games_per_matchup))
+    }
+
+    /// Works out every matchup exactly, by dealing every board to every
+    /// pair of combos, spread over the given number of threads. Boards that
+    /// only differ by how their suits are named are dealt once, so there are
+    /// `NUM_DISTINCT_BOARDS` of them. `on_board` is called after each one,
+    /// from whichever thread dealt it.
+    pub fn enumerate<F: Fn() + Sync>(num_threads: usize, on_board: F) -> Self {
+        assert!(num_threads > 0, "Must have at least one thread");
+        let boards = distinct_boards();
+        let combos = all_combos();
+        let (boards, combos, on_board) = (&boards, &combos, &on_board);
+
+        let mut showdowns = Showdowns::new();
+        thread::scope(|scope| {
+            let handles = (0..num_threads)
+                .map(|thread| {
+                    scope.spawn(move || {
+                        let mut showdowns = Showdowns::new();
+                        for (board, weight) in boards.iter().skip(thread).step_by(num_threads) {
+                            showdowns.add_board(board, *weight, combos);
+                            on_board();
+                        }
+                        showdowns
+                    })
+                })
+                .collect::<Vec<_>>();
+            for handle in handles {
+                showdowns.merge(&handle.join().expect("Board thread panicked"));
+            }
+        });
+
+        let equities = Self::matchups()
+            .into_iter()
+            .map(|(hand, other)| showdowns.equity(hand, other));
+        Self::from_matchups(equities, None)
+    }
+
+    /// Returns the equity of `hand` when all in against `other`.
+    pub fn get(&self, hand: StartingHand, other: StartingHand) -> f64 {
+        self.equities[hand.index() * NUM_STARTING_HANDS + other.index()]
+    }
+
+    /// Returns the equity of `hand` when all in against a random hand,
+    /// weighting each of the opponent's starting hands by how many of its
+    /// combos are left.
+    pub fn against_random(&self, hand: StartingHand) -> f64 {
+        let (pot_shares, num_combos) =
+            StartingHand::all()
+                .iter()
+                .fold((0.0, 0.0), |(pot_shares, num_combos), &other| {
+                    let combos = other.num_combos_given(hand) as f64;
+                    (
+                        pot_shares + combos * self.get(hand, other),
+                        num_combos + combos,
+                    )
+                });
+        pot_shares / num_combos
+    }
+
+    /// Writes the table in a compact binary layout: `TABLE_MAGIC`, then
+    /// `TABLE_VERSION` and the games per matchup (zero if exact) as
+    /// little-endian integers, then each matchup's equity as a little-endian
+    /// fraction of `u16::MAX`, in the order of `StartingHand::all()`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(TABLE_MAGIC)?;
+        writer.write_all(&TABLE_VERSION.to_le_bytes())?;
+        let games_per_matchup = self.games_per_matchup.unwrap_or(0) as u64;
+        writer.write_all(&games_per_matchup.to_le_bytes())?;
+        for (hand, other) in Self::matchups() {
+            let equity = (self.get(hand, other) * f64::from(u16::MAX)).round() as u16;
+            writer.write_all(&equity.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a table written by `write_to()`.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        // Running out of bytes means the file was cut short, or isn't a table
+        // at all.
+        let mut read_exact = |buffer: &mut [u8], message: &str| {
+            reader.read_exact(buffer).map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => invalid(String::from(message)),
+                _ => e,
+            })
+        };
+
+        let mut magic = [0; 4];
+        read_exact(&mut magic, "not a preflop equity table")?;
+        if &magic != TABLE_MAGIC {
+            return Err(invalid(String::from("not a preflop equity table")));
+        }
+        let mut version = [0; 2];
+        read_exact(&mut version, "not a preflop equity table")?;
+        let version = u16::from_le_bytes(version);
+        if version != TABLE_VERSION {
+            return Err(invalid(format!(
+                "unsupported table version {} (expected {})",
+                version, TABLE_VERSION
+            )));
+        }
+        let mut games_per_matchup = [0; 8];
+        read_exact(&mut games_per_matchup, "the table is cut short")?;
+        let games_per_matchup = match u64::from_le_bytes(games_per_matchup) {
+            0 => None,
+            games => Some(games as usize),
+        };
+
+        let mut equities = vec![0; NUM_MATCHUPS * 2];
+        read_exact(&mut equities, "the table is cut short")?;
+        if reader.read(&mut [0])? > 0 {
+            return Err(invalid(String::from("unexpected data after the table")));
+        }
+        let equities = equities
+            .chunks(2)
+            .map(|bytes| f64::from(u16::from_le_bytes([bytes[0], bytes[1]])) / f64::from(u16::MAX));
+        Ok(Self::from_matchups(equities, games_per_matchup))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Works out the equity of every matchup, in the order of
+/// `HeadsUpEquities::matchups()`, spread over the given number of threads.
+/// Each thread starts with its own state from `new_state`, and the matchups
+/// are interleaved so that every thread gets its share of the slow ones.
+fn in_parallel<S, N, F>(num_threads: usize, new_state: N, equity: F) -> Vec<f64>
+where
+    N: Fn(usize) -> S + Sync,
+    F: Fn(&mut S, StartingHand, StartingHand) -> f64 + Sync,
+{
+    assert!(num_threads > 0, "Must have at least one thread");
+    let matchups = HeadsUpEquities::matchups();
+    let (new_state, equity) = (&new_state, &equity);
+
+    let mut equities = vec![0.0; matchups.len()];
+    thread::scope(|scope| {
+        let handles = (0..num_threads)
+            .map(|thread| {
+                let matchups = &matchups;
+                scope.spawn(move || {
+                    let mut state = new_state(thread);
+                    (thread..matchups.len())
+                        .step_by(num_threads)
+                        .map(|index| {
+                            let (hand, other) = matchups[index];
+                            (index, equity(&mut state, hand, other))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            for (index, matchup_equity) in handle.join().expect("Matchup thread panicked") {
+                equities[index] = matchup_equity;
+            }
+        }
+    });
+    equities
+}
+
+/// Estimates the equity of `hand` against `other` by playing the given
+/// number of games.
+fn simulate_matchup<R: Rng>(
+    hand: StartingHand,
+    other: StartingHand,
+    num_games: usize,
+    random: &mut R,
+) -> f64 {
+    assert!(num_games > 0, "Must play at least one game");
+    let matchups = hand
+        .combos()
+        .iter()
+        .flat_map(|&combo| other.combos().into_iter().map(move |o| (combo, o)))
+        .filter(|(combo, o)| !combo.iter().any(|card| o.contains(card)))
+        .collect::<Vec<_>>();
+
+    let mut pot_shares = 0.0;
+    for _ in 0..num_games {
+        let (combo, other_combo) = matchups[random.gen_range(matchups.len())];
+        let mut deck = remove_from_deck(Card::new_deck(), [combo, other_combo].concat());
+        let board = random.deal(&mut deck, NUM_COMMUNITY_CARDS);
+        pot_shares += showdown(board, &combo, &other_combo);
+    }
+    pot_shares / num_games as f64
+}
+
+/// Our share of the pot when our combo and the other meet on the given
+/// board.
+fn showdown(board: &[Card], combo: &[Card], other_combo: &[Card]) -> f64 {
+    let ours = Hand::from([board, combo].concat());
+    let theirs = Hand::from([board, other_combo].concat());
+    match ours.cmp(&theirs) {
+        Ordering::Greater => 1.0,
+        Ordering::Equal => 0.5,
+        Ordering::Less => 0.0,
+    }
+}
+
+/// How many boards there are once boards that only differ by how their
+/// suits are named count as one.
+pub const NUM_DISTINCT_BOARDS: usize = 134_459;
+
+fn card_index(card: Card) -> usize {
+    card.value as usize * ALL_SUITS.len() + card.suit as usize
+}
+
+/// Every way of renaming the four suits, as the new index of each suit.
+fn suit_renamings() -> Vec<[usize; 4]> {
+    (0..256)
+        .map(|n| [n % 4, n / 4 % 4, n / 16 % 4, n / 64])
+        .filter(|renaming| (0..4).all(|suit| renaming.contains(&suit)))
+        .collect()
+}
+
+/// Returns one board of each group that only differ by how their suits are
+/// named, along with how many boards are in its group. Starting hands don't
+/// care how suits are named either, so a matchup does as well on every
+/// board of a group.
+fn distinct_boards() -> Vec<([Card; NUM_COMMUNITY_CARDS], u64)> {
+    let renamings = suit_renamings();
+    let deck = Card::new_deck();
+    let mut boards = Combinations::new(&deck, NUM_COMMUNITY_CARDS);
+    let mut distinct = Vec::with_capacity(NUM_DISTINCT_BOARDS);
+    while let Some(board) = boards.advance() {
+        let renamed = |renaming: &[usize; 4]| {
+            let mut indices = [0; NUM_COMMUNITY_CARDS];
+            for (index, card) in indices.iter_mut().zip(board.iter()) {
+                *index = card.value as usize * ALL_SUITS.len() + renaming[card.suit as usize];
+            }
+            indices.sort_unstable();
+            indices
+        };
+        // Keep the board whose sorted card indices come first, and count
+        // the renamings that leave it as it is.
+        let own = renamed(&[0, 1, 2, 3]);
+        let mut unchanged = 0;
+        let is_first = renamings.iter().all(|renaming| {
+            let indices = renamed(renaming);
+            unchanged += usize::from(indices == own);
+            indices >= own
+        });
+        if is_first {
+            let mut cards = [board[0]; NUM_COMMUNITY_CARDS];
+            cards.copy_from_slice(board);
+            distinct.push((cards, (renamings.len() / unchanged) as u64));
+        }
+    }
+    distinct
+}
+
+/// Every combo of hole cards, along with the index of its starting hand.
+fn all_combos() -> Vec<([Card; 2], usize)> {
+    StartingHand::all()
+        .iter()
+        .flat_map(|hand| {
+            hand.combos()
+                .into_iter()
+                .map(move |combo| (combo, hand.index()))
+        })
+        .collect()
+}
+
+/// Counts, for every pair of starting hands, how often one of the first
+/// hand's combos beats or ties one of the second's, over pairs of combos
+/// that don't share a card and boards that share a card with neither.
+struct Showdowns {
+    wins: Vec<u64>,
+    ties: Vec<u64>,
+}
+
+impl Showdowns {
+    fn new() -> Self {
+        Showdowns {
+            wins: vec![0; NUM_STARTING_HANDS * NUM_STARTING_HANDS],
+            ties: vec![0; NUM_STARTING_HANDS * NUM_STARTING_HANDS],
+        }
+    }
+
+    /// Plays every pair of combos on the board, counting each showdown
+    /// `weight` times. Rather than comparing every pair, we rank the combos
+    /// once and sweep through them from weakest to strongest, keeping count
+    /// of the weaker combos of each hand. Combos that share a card with ours
+    /// are then taken back out of those counts.
+    fn add_board(&mut self, board: &[Card], weight: u64, combos: &[([Card; 2], usize)]) {
+        const N: usize = NUM_STARTING_HANDS;
+        let mut ranked = combos
+            .iter()
+            .filter(|(combo, _)| !combo.iter().any(|card| board.contains(card)))
+            .map(|&(combo, hand)| {
+                let cards = Hand::from([board, &combo].concat());
+                let category = cards.find_best_category().expect("Hand is non-empty");
+                let kickers = category
+                    .get_kickers(&cards)
+                    .iter()
+                    .map(|card| card.value)
+                    .collect::<Vec<_>>();
+                ((category, kickers), combo.map(card_index), hand)
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut weaker = vec![0; N];
+        let mut weaker_by_card = vec![0; 52 * N];
+        let mut tied = vec![0; N];
+        let mut tied_by_card = vec![0; 52 * N];
+        for group in ranked.chunk_by(|a, b| a.0 == b.0) {
+            for &(_, cards, hand) in group {
+                tied[hand] += 1;
+                tied_by_card[cards[0] * N + hand] += 1;
+                tied_by_card[cards[1] * N + hand] += 1;
+            }
+            for &(_, cards, hand) in group {
+                let (first, second) = (cards[0] * N, cards[1] * N);
+                let wins = &mut self.wins[hand * N..(hand + 1) * N];
+                let ties = &mut self.ties[hand * N..(hand + 1) * N];
+                for other in 0..N {
+                    wins[other] += weight
+                        * (weaker[other]
+                            - weaker_by_card[first + other]
+                            - weaker_by_card[second + other]);
+                    // Our own combo shares both of its cards with itself.
+                    let ours = u64::from(other == hand);
+                    ties[other] += weight
+                        * (tied[other] + ours
+                            - tied_by_card[first + other]
+                            - tied_by_card[second + other]);
+                }
+            }
+            for &(_, cards, hand) in group {
+                tied[hand] -= 1;
+                tied_by_card[cards[0] * N + hand] -= 1;
+                tied_by_card[cards[1] * N + hand] -= 1;
+                weaker[hand] += 1;
+                weaker_by_card[cards[0] * N + hand] += 1;
+                weaker_by_card[cards[1] * N + hand] += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Showdowns) {
+        for (total, count) in self.wins.iter_mut().zip(other.wins.iter()) {
+            *total += count;
+        }
+        for (total, count) in self.ties.iter_mut().zip(other.ties.iter()) {
+            *total += count;
+        }
+    }
+
+    /// Our share of the pot from every showdown of `hand` against `other`.
+    fn equity(&self, hand: StartingHand, other: StartingHand) -> f64 {
+        let index = |hand: StartingHand, other: StartingHand| {
+            hand.index() * NUM_STARTING_HANDS + other.index()
+        };
+        let wins = self.wins[index(hand, other)] as f64;
+        let ties = self.ties[index(hand, other)] as f64;
+        let losses = self.wins[index(other, hand)] as f64;
+        (wins + ties / 2.0) / (wins + ties + losses)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::StartingHand;
+
+    impl Serialize for StartingHand {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.label())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for StartingHand {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer)?
+                .parse::<StartingHand>()
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::{
+        all_combos, distinct_boards, showdown, HeadsUpEquities, Showdowns, StartingHand,
+        NUM_DISTINCT_BOARDS,
+    };
+
+    fn starting_hand(value: &'static str) -> StartingHand {
+        let cards = Card::try_vec_from(value).unwrap();
+        StartingHand::new(cards[0], cards[1])
+    }
+
+    #[test]
+    fn test_label_works() {
+        assert_eq!(starting_hand("kh ad").label(), "AKo");
+        assert_eq!(starting_hand("ks as").label(), "AKs");
+        assert_eq!(starting_hand("10d 9d").label(), "T9s");
+        assert_eq!(starting_hand("7c 7d").label(), "77");
+    }
+
+    #[test]
+    fn test_parse_works() {
+        assert_eq!("AKs".parse::<StartingHand>(), Ok(starting_hand("as ks")));
+        assert_eq!("9To".parse::<StartingHand>(), Ok(starting_hand("10s 9h")));
+        assert_eq!("77".parse::<StartingHand>(), Ok(starting_hand("7s 7h")));
+        assert!("77s".parse::<StartingHand>().is_err());
+        assert!("AK".parse::<StartingHand>().is_err());
+    }
+
+    #[test]
+    fn test_sklansky_group_works() {
+        assert_eq!(starting_hand("as ks").sklansky_group(), Some(1));
+        assert_eq!(starting_hand("jd 10d").sklansky_group(), Some(3));
+        assert_eq!(starting_hand("6h 5h").sklansky_group(), Some(5));
+        assert_eq!(starting_hand("5h 4d").sklansky_group(), Some(8));
+        assert_eq!(starting_hand("7h 2d").sklansky_group(), None);
+    }
+
+    #[test]
+    fn test_chen_score_works() {
+        assert_eq!(starting_hand("as ah").chen_score(), 20);
+        assert_eq!(starting_hand("as ks").chen_score(), 12);
+        assert_eq!(starting_hand("10s 9s").chen_score(), 8);
+        assert_eq!(starting_hand("5s 7s").chen_score(), 6);
+        assert_eq!(starting_hand("2s 2h").chen_score(), 5);
+        assert_eq!(starting_hand("7s 2h").chen_score(), -1);
+    }
+
+    #[test]
+    fn test_equity_table_covers_every_starting_hand() {
+        let deck = Card::new_deck();
+        for first in deck.iter() {
+            for second in deck.iter().filter(|card| *card != first) {
+                let hand = StartingHand::new(*first, *second);
+                assert!(hand.equity_rank() >= 1 && hand.equity_rank() <= 169);
+            }
+        }
+        assert_eq!(starting_hand("ad ah").equity_rank(), 1);
+        assert_eq!(starting_hand("3d 2h").equity_rank(), 169);
+    }
+
+    #[test]
+    fn test_grid_order_works() {
+        let hands = StartingHand::all();
+        assert_eq!(hands[0].label(), "AA");
+        assert_eq!(hands[1].label(), "AKs");
+        assert_eq!(hands[13].label(), "AKo");
+        assert_eq!(hands[168].label(), "22");
+        for (index, hand) in hands.iter().enumerate() {
+            assert_eq!(hand.index(), index);
+        }
+    }
+
+    #[test]
+    fn test_combos_work() {
+        let combos = |label: &str| label.parse::<StartingHand>().unwrap().combos().len();
+        assert_eq!(combos("AA"), 6);
+        assert_eq!(combos("AKs"), 4);
+        assert_eq!(combos("AKo"), 12);
+        let total: usize = StartingHand::all()
+            .iter()
+            .map(|hand| hand.combos().len())
+            .sum();
+        assert_eq!(total, 1326);
+
+        let hand = |label: &str| label.parse::<StartingHand>().unwrap();
+        assert_eq!(hand("AA").num_combos_given(hand("AKs")), 3);
+        assert_eq!(hand("AKo").num_combos_given(hand("AKs")), 6);
+    }
+
+    #[test]
+    fn test_distinct_boards_cover_every_board() {
+        let boards = distinct_boards();
+        assert_eq!(boards.len(), NUM_DISTINCT_BOARDS);
+        let total = boards.iter().map(|&(_, weight)| weight).sum::<u64>();
+        assert_eq!(total, 2_598_960);
+    }
+
+    #[test]
+    fn test_showdowns_match_playing_every_pair() {
+        let combos = all_combos();
+        let boards = ["2c 7d 9h js ks", "ah kh qh 3c 3d", "5s 5h 5d 8c 8s"]
+            .iter()
+            .map(|board| Card::try_vec_from(board).unwrap())
+            .collect::<Vec<_>>();
+        let mut showdowns = Showdowns::new();
+        for board in boards.iter() {
+            showdowns.add_board(board, 1, &combos);
+        }
+
+        for &(hand, other) in [("AA", "KK"), ("AKs", "QJo"), ("88", "55"), ("KQs", "AKo")].iter() {
+            let (hand, other) = (hand.parse().unwrap(), other.parse().unwrap());
+            let (mut pot_shares, mut num_showdowns) = (0.0, 0);
+            for board in boards.iter() {
+                for combo in StartingHand::combos(&hand) {
+                    for other_combo in StartingHand::combos(&other) {
+                        let cards = [&combo[..], &other_combo[..]].concat();
+                        let shares_a_card = cards
+                            .iter()
+                            .enumerate()
+                            .any(|(i, card)| board.contains(card) || cards[i + 1..].contains(card));
+                        if !shares_a_card {
+                            pot_shares += showdown(board, &combo, &other_combo);
+                            num_showdowns += 1;
+                        }
+                    }
+                }
+            }
+            let equity = showdowns.equity(hand, other);
+            assert!((equity - pot_shares / num_showdowns as f64).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_equity_table_round_trips() {
+        let equities = HeadsUpEquities::simulate(1, &mut Xorshift::from_seed(1));
+        let mut bytes = vec![];
+        equities.write_to(&mut bytes).unwrap();
+        let loaded = HeadsUpEquities::read_from(&bytes[..]).unwrap();
+
+        assert_eq!(loaded.games_per_matchup, Some(1));
+        for hand in StartingHand::all() {
+            for other in StartingHand::all() {
+                assert!((loaded.get(hand, other) - equities.get(hand, other)).abs() < 1e-4);
+            }
+        }
+
+        let error = |bytes: &[u8]| HeadsUpEquities::read_from(bytes).err().unwrap().kind();
+        assert_eq!(error(&bytes[1..]), io::ErrorKind::InvalidData);
+        let mut newer = bytes.clone();
+        newer[4] += 1;
+        assert_eq!(error(&newer), io::ErrorKind::InvalidData);
+        assert_eq!(error(&bytes[..bytes.len() - 1]), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error(&[&bytes[..], &[0]].concat()),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Tracks a running simulation so that we can report how far along it is,
+//! show the equity estimate so far, and stop it early.
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::texas::Deal;
+use super::variance::Estimate;
+
+/// We count shares of the pot in units of 1/2520 of it, since 2520 divides
+/// evenly by every number of ways a pot can be split at a table of ten.
+/// That lets us add them up exactly with atomic integers.
+pub(crate) const POT_SHARE_UNITS: u64 = 2520;
+
+/// How many games a thread counts on its own before adding them to the
+/// shared counters, so that the threads don't contend for them every game.
+const BATCH_SIZE: usize = 1024;
+
+/// Counts the games played so far. Every simulation thread adds its games
+/// to the same counters through a `LocalProgress`, so it can be shared with
+/// a thread that reports on them.
+pub struct Progress {
+    num_iterations: usize,
+    completed: AtomicUsize,
+    pot_shares: AtomicU64,
+    squared_pot_shares: AtomicU64,
+    stopped: AtomicBool,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(num_iterations: usize) -> Self {
+        Progress {
+            num_iterations,
+            completed: AtomicUsize::new(0),
+            pot_shares: AtomicU64::new(0),
+            squared_pot_shares: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns a counter for one simulation thread's games.
+    pub fn local(&self) -> LocalProgress<'_> {
+        LocalProgress {
+            progress: self,
+            completed: 0,
+            pot_shares: 0,
+            squared_pot_shares: 0,
+        }
+    }
+
+    /// Asks the simulation to stop after the games it is playing.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    pub fn games_per_second(&self) -> f64 {
+        self.completed() as f64 / self.started.elapsed().as_secs_f64()
+    }
+
+    /// Estimates how long the rest of the games will take at the rate
+    /// they've been played so far.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let rate = self.games_per_second();
+        if rate > 0.0 {
+            let remaining = self.num_iterations.saturating_sub(self.completed());
+            Some(Duration::from_secs_f64(remaining as f64 / rate))
+        } else {
+            None
+        }
+    }
+
+    /// Returns our pot equity over the games played so far.
+    pub fn equity(&self) -> Estimate {
+        let samples = self.completed();
+        let n = samples.max(1) as f64;
+        let units = POT_SHARE_UNITS as f64;
+        let mean = self.pot_shares.load(Ordering::Relaxed) as f64 / units / n;
+        let mean_of_squares =
+            self.squared_pot_shares.load(Ordering::Relaxed) as f64 / (units * units) / n;
+        Estimate {
+            mean,
+            variance: (mean_of_squares - mean * mean).max(0.0) / n,
+            samples,
+        }
+    }
+
+    /// Describes how far along we are, optionally with the equity so far,
+    /// e.g. "1200000 of 10000000 games (12.0%), 180000 games/s, 0:48 left".
+    pub fn status(&self, with_equity: bool) -> String {
+        let completed = self.completed();
+        let mut status = format!(
+            "{} of {} games ({:.1}%), {:.0} games/s",
+            completed,
+            self.num_iterations,
+            completed as f64 / self.num_iterations.max(1) as f64 * 100.0,
+            self.games_per_second()
+        );
+        if let Some(remaining) = self.time_remaining() {
+            let seconds = remaining.as_secs();
+            status.push_str(&format!(", {}:{:02} left", seconds / 60, seconds % 60));
+        }
+        if with_equity && completed > 0 {
+            let equity = self.equity();
+            let (low, high) = equity.confidence_interval();
+            status.push_str(&format!(
+                ", equity {:.2}% (95% CI {:.2}% to {:.2}%)",
+                equity.mean * 100.0,
+                low * 100.0,
+                high * 100.0
+            ));
+        }
+        status
+    }
+}
+
+/// Counts the games played on one thread, and adds them to its `Progress`
+/// every `BATCH_SIZE` games and when it's dropped.
+pub struct LocalProgress<'a> {
+    progress: &'a Progress,
+    completed: usize,
+    pot_shares: u64,
+    squared_pot_shares: u64,
+}
+
+impl LocalProgress<'_> {
+    /// Counts a game that has been played, and tells the simulation whether
+    /// to keep going.
+    pub fn record(&mut self, deal: &Deal) -> ControlFlow<()> {
+        let share = (deal.pot_share * POT_SHARE_UNITS as f64).round() as u64;
+        self.pot_shares += share;
+        self.squared_pot_shares += share * share;
+        self.completed += 1;
+        if self.completed == BATCH_SIZE {
+            self.publish();
+        }
+
+        if self.progress.is_stopped() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn publish(&mut self) {
+        let progress = self.progress;
+        progress
+            .pot_shares
+            .fetch_add(self.pot_shares, Ordering::Relaxed);
+        progress
+            .squared_pot_shares
+            .fetch_add(self.squared_pot_shares, Ordering::Relaxed);
+        progress
+            .completed
+            .fetch_add(self.completed, Ordering::Relaxed);
+        self.completed = 0;
+        self.pot_shares = 0;
+        self.squared_pot_shares = 0;
+    }
+}
+
+impl Drop for LocalProgress<'_> {
+    fn drop(&mut self) {
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::super::texas::simulate_with;
+    use super::Progress;
+
+    #[test]
+    fn test_progress_counts_and_stops() {
+        let hole_cards = Card::try_vec_from("as ad").unwrap();
+        let progress = Progress::new(100);
+        let mut local = progress.local();
+        let mut played = 0;
+        let results = simulate_with(
+            &hole_cards,
+            &[],
+            3,
+            100,
+            &mut Xorshift::from_seed(1),
+            |deal| {
+                played += 1;
+                if played == 10 {
+                    progress.stop();
+                }
+                local.record(deal)
+            },
+        );
+        drop(local);
+
+        assert_eq!(results.num_iterations, 10);
+        assert_eq!(progress.completed(), 10);
+        assert!((progress.equity().mean - results.equity().mean).abs() < 1e-12);
+        assert!(progress.status(true).starts_with("10 of 100 games (10.0%)"));
+    }
+}
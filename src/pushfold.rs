@@ -0,0 +1,309 @@
+//! Solves the push/fold game that short stacks play: the small blind either
+//! goes all in or folds, and the big blind either calls or folds.
+// https://en.wikipedia.org/wiki/Nash_equilibrium
+use super::preflop::{print_grid, HeadsUpEquities, StartingHand, NUM_STARTING_HANDS};
+
+/// How many combos of hole cards there are, i.e. 52 choose 2, and how many
+/// an opponent could have once ours are dealt, i.e. 50 choose 2.
+const NUM_COMBOS: f64 = 1326.0;
+const NUM_OPPONENT_COMBOS: f64 = 1225.0;
+
+/// How many rounds of fictitious play `solve` needs to get within a small
+/// fraction of a big blind of the equilibrium.
+pub const NUM_SOLVER_ITERATIONS: usize = 1000;
+
+/// The blinds, in big blinds.
+const SMALL_BLIND: f64 = 0.5;
+const BIG_BLIND: f64 = 1.0;
+
+/// A push/fold spot once everyone else has folded to the small blind.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PushFoldGame {
+    /// The effective stack in big blinds, before posting blinds and antes.
+    pub stack: f64,
+    /// The ante each player posts, in big blinds.
+    pub ante: f64,
+    /// How many players were dealt in, whose antes are in the pot. Only the
+    /// blinds are left to act.
+    pub num_players: usize,
+}
+
+impl PushFoldGame {
+    /// The pot once both blinds are all in.
+    fn all_in_pot(&self) -> f64 {
+        2.0 * (self.stack - self.ante) + self.num_players as f64 * self.ante
+    }
+
+    /// What the small blind wins when the big blind folds to the shove.
+    fn steal(&self) -> f64 {
+        BIG_BLIND + (self.num_players - 1) as f64 * self.ante
+    }
+
+    /// What a player wins or loses, compared to their stack before the hand,
+    /// by getting all in with the given equity.
+    fn all_in(&self, equity: f64) -> f64 {
+        equity * self.all_in_pot() - self.stack
+    }
+}
+
+/// How often each starting hand shoves or calls, indexed as in
+/// `StartingHand::all()`.
+pub struct Strategies {
+    pub push: Vec<f64>,
+    pub call: Vec<f64>,
+    /// How much either player could gain, in big blinds per hand, by
+    /// deviating from these strategies. It is zero at an exact equilibrium.
+    pub exploitability: f64,
+}
+
+impl Strategies {
+    /// The share of all hands that are played with the given frequencies.
+    pub fn range_size(frequencies: &[f64]) -> f64 {
+        StartingHand::all()
+            .iter()
+            .zip(frequencies.iter())
+            .map(|(hand, frequency)| hand.combos().len() as f64 * frequency)
+            .sum::<f64>()
+            / NUM_COMBOS
+    }
+}
+
+/// The chances of each of the opponent's starting hands given ours, and our
+/// equity against them.
+struct Matchups {
+    /// `weights[i][j]` is the chance that the opponent has hand `j` when we
+    /// have hand `i`.
+    weights: Vec<Vec<f64>>,
+    equities: Vec<Vec<f64>>,
+}
+
+impl Matchups {
+    fn new(equities: &HeadsUpEquities) -> Self {
+        let hands = StartingHand::all();
+        Matchups {
+            weights: hands
+                .iter()
+                .map(|&hand| {
+                    hands
+                        .iter()
+                        .map(|other| other.num_combos_given(hand) as f64 / NUM_OPPONENT_COMBOS)
+                        .collect()
+                })
+                .collect(),
+            equities: hands
+                .iter()
+                .map(|&hand| {
+                    hands
+                        .iter()
+                        .map(|&other| equities.get(hand, other))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// The small blind's result from shoving each hand, against a big blind
+    /// who calls with the given frequencies.
+    fn push_values(&self, game: &PushFoldGame, call: &[f64]) -> Vec<f64> {
+        (0..NUM_STARTING_HANDS)
+            .map(|i| {
+                (0..NUM_STARTING_HANDS)
+                    .map(|j| {
+                        self.weights[i][j]
+                            * (call[j] * game.all_in(self.equities[i][j])
+                                + (1.0 - call[j]) * game.steal())
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The big blind's result from calling with each hand, and the chance
+    /// of facing a shove with it, against a small blind who shoves with the
+    /// given frequencies.
+    fn call_values(&self, game: &PushFoldGame, push: &[f64]) -> Vec<(f64, f64)> {
+        (0..NUM_STARTING_HANDS)
+            .map(|j| {
+                (0..NUM_STARTING_HANDS).fold((0.0, 0.0), |(value, chance), i| {
+                    let weight = self.weights[j][i] * push[i];
+                    (
+                        value + weight * game.all_in(self.equities[j][i]),
+                        chance + weight,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// The small blind's best response to the given calling frequencies,
+    /// along with its value per hand.
+    fn best_push(&self, game: &PushFoldGame, call: &[f64]) -> (Vec<f64>, f64) {
+        let fold = -(SMALL_BLIND + game.ante);
+        let values = self.push_values(game, call);
+        let push = values
+            .iter()
+            .map(|&value| if value > fold { 1.0 } else { 0.0 })
+            .collect();
+        (
+            push,
+            self.average(values.iter().map(|&value| value.max(fold))),
+        )
+    }
+
+    /// The big blind's best response to the given shoving frequencies, along
+    /// with the small blind's value per hand against it.
+    fn best_call(&self, game: &PushFoldGame, push: &[f64]) -> (Vec<f64>, f64) {
+        let fold = -(BIG_BLIND + game.ante);
+        let call = self
+            .call_values(game, push)
+            .iter()
+            .map(|&(value, chance)| if value > fold * chance { 1.0 } else { 0.0 })
+            .collect::<Vec<_>>();
+        let values = self.push_values(game, &call);
+        let value = self.average(
+            values
+                .iter()
+                .zip(push.iter())
+                .map(|(&value, &push)| push * value - (1.0 - push) * (SMALL_BLIND + game.ante)),
+        );
+        (call, value)
+    }
+
+    /// Averages values per starting hand over all combos.
+    fn average<I: Iterator<Item = f64>>(&self, values: I) -> f64 {
+        StartingHand::all()
+            .iter()
+            .zip(values)
+            .map(|(hand, value)| hand.combos().len() as f64 * value)
+            .sum::<f64>()
+            / NUM_COMBOS
+    }
+}
+
+/// Finds the equilibrium by fictitious play: each player repeatedly plays a
+/// best response to the average of the other's strategies so far, and those
+/// averages converge to the equilibrium.
+pub fn solve(game: &PushFoldGame, equities: &HeadsUpEquities, num_iterations: usize) -> Strategies {
+    let matchups = Matchups::new(equities);
+    let mut push = vec![1.0; NUM_STARTING_HANDS];
+    let mut call = vec![0.0; NUM_STARTING_HANDS];
+
+    for iteration in 1..=num_iterations {
+        let (best_push, _) = matchups.best_push(game, &call);
+        let (best_call, _) = matchups.best_call(game, &push);
+        let step = 1.0 / iteration as f64;
+        for i in 0..NUM_STARTING_HANDS {
+            push[i] += step * (best_push[i] - push[i]);
+            call[i] += step * (best_call[i] - call[i]);
+        }
+    }
+
+    // Each player's best response shows how far the other is from the
+    // equilibrium.
+    let (_, best_push_value) = matchups.best_push(game, &call);
+    let (_, best_call_value) = matchups.best_call(game, &push);
+    Strategies {
+        push,
+        call,
+        exploitability: best_push_value - best_call_value,
+    }
+}
+
+/// A hand counts as part of a range if it's played more often than not.
+fn in_range(frequency: f64) -> bool {
+    frequency >= 0.5
+}
+
+pub fn print_strategies(
+    game: &PushFoldGame,
+    strategies: &Strategies,
+    games_per_matchup: Option<usize>,
+) {
+    let table = if game.num_players == 2 {
+        String::from("heads up")
+    } else {
+        format!(
+            "folded to the small blind at a table of {}",
+            game.num_players
+        )
+    };
+    let antes = if game.ante > 0.0 {
+        format!(" and {} BB antes", game.ante)
+    } else {
+        String::new()
+    };
+    let equities = match games_per_matchup {
+        Some(games) => format!("comes from {} games", games),
+        None => String::from("is exact"),
+    };
+    println!(
+        "Push/fold equilibrium with {} BB stacks{}, {}. Each matchup's equity {}.",
+        game.stack, antes, table, equities
+    );
+
+    for (title, frequencies) in [
+        ("Small blind shoves", &strategies.push),
+        ("Big blind calls the shove with", &strategies.call),
+    ]
+    .iter()
+    {
+        let in_range = frequencies
+            .iter()
+            .map(|&frequency| if in_range(frequency) { 1.0 } else { 0.0 })
+            .collect::<Vec<_>>();
+        println!();
+        println!(
+            "{} {:.1}% of hands:\n",
+            title,
+            Strategies::range_size(&in_range) * 100.0
+        );
+        print_grid(|hand| {
+            if in_range[hand.index()] > 0.0 {
+                hand.label()
+            } else {
+                String::from(".")
+            }
+        });
+    }
+
+    println!();
+    println!(
+        "Either player gains at most {:.4} BB per hand by deviating from these ranges.",
+        strategies.exploitability
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::preflop::{HeadsUpEquities, StartingHand};
+    use super::super::random::{FromSeed, Xorshift};
+    use super::{solve, PushFoldGame};
+
+    #[test]
+    fn test_short_stacks_shove_wide_and_deep_stacks_tight() {
+        let equities = HeadsUpEquities::simulate(4, &mut Xorshift::from_seed(1));
+        let strategies = |stack| {
+            solve(
+                &PushFoldGame {
+                    stack,
+                    ante: 0.0,
+                    num_players: 2,
+                },
+                &equities,
+                200,
+            )
+        };
+        let short = strategies(2.0);
+        let deep = strategies(20.0);
+
+        let index = |label: &str| label.parse::<StartingHand>().unwrap().index();
+        assert!(short.push[index("AA")] > 0.99);
+        assert!(deep.push[index("AA")] > 0.99);
+        assert!(deep.call[index("AA")] > 0.99);
+        assert!(deep.push[index("72o")] < 0.01);
+        let total = |frequencies: &[f64]| frequencies.iter().sum::<f64>();
+        assert!(total(&short.push) > total(&deep.push));
+        assert!(total(&short.call) > total(&deep.call));
+        assert!(deep.exploitability < 0.05);
+    }
+}
@@ -1,36 +1,255 @@
-// https://en.wikipedia.org/wiki/Linear_congruential_generator
-use chrono::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of random numbers for the simulator.
+pub trait Rng {
+    /// Returns the next 64 random bits.
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a uniformly distributed random number in the range `0..n`.
+    ///
+    /// This uses Lemire's multiply-and-reject method, which avoids the bias
+    /// of `next_u64() % n`. See https://arxiv.org/abs/1805.10941.
+    fn gen_range(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Range must be non-empty");
+        let n = n as u64;
+        let mut product = self.next_u64() as u128 * n as u128;
+        if (product as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (product as u64) < threshold {
+                product = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (product >> 64) as usize
+    }
+
+    /// Uniformly shuffles the slice using the Fisher-Yates algorithm.
+    fn shuffle<T>(&mut self, v: &mut [T])
+    where
+        Self: Sized,
+    {
+        self.deal(v, v.len());
+    }
 
+    /// Moves `k` uniformly chosen items to the front of the slice, in random
+    /// order, and returns them. This does only as much work as is needed to
+    /// choose those items, so it's much cheaper than a full shuffle when `k`
+    /// is small.
+    ///
+    /// The rest of the slice remains a permutation of the unchosen items, so
+    /// the same slice can be dealt from over and over again.
+    fn deal<'a, T>(&mut self, v: &'a mut [T], k: usize) -> &'a [T]
+    where
+        Self: Sized,
+    {
+        assert!(k <= v.len(), "Cannot deal more items than there are");
+        for i in 0..k.min(v.len().saturating_sub(1)) {
+            let j = i + self.gen_range(v.len() - i);
+            v.swap(i, j);
+        }
+        &v[..k]
+    }
+}
+
+/// A generator that can be deterministically created from a seed.
+pub trait FromSeed {
+    fn from_seed(seed: u64) -> Self;
+}
+
+/// Returns a seed for runs that weren't given one, based on the current
+/// time in nanoseconds so that runs started close together still differ.
+pub fn new_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// Derives an independent seed for one of several streams of random numbers
+/// (e.g. one per thread) from a master seed.
+pub fn derive_seed(seed: u64, stream: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(stream))
+}
+
+// https://prng.di.unimi.it/splitmix64.c
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// https://en.wikipedia.org/wiki/Linear_congruential_generator
+//
 // These parameters are from Numerical Recipes.
 const MODULUS: u64 = 2u64.pow(32);
 const MULTIPLIER: u64 = 1664525;
 const INCREMENT: u64 = 1013904223;
 
+/// A fast but statistically weak 32-bit linear congruential generator.
 pub struct Random {
     pub seed: u64,
 }
 
+impl Default for Random {
+    fn default() -> Self {
+        Random::new()
+    }
+}
+
 impl Random {
     pub fn new() -> Self {
-        Random {
-            seed: Utc::now().timestamp() as u64,
-        }
+        Random::from_seed(new_seed())
     }
 
-    pub fn next(&mut self) -> u64 {
+    fn next_u32(&mut self) -> u64 {
         self.seed = (MULTIPLIER * self.seed + INCREMENT) % MODULUS;
         self.seed
     }
+}
 
-    pub fn shuffle<T>(&mut self, v: &mut Vec<T>) {
-        let mut i = 0;
-        let attempts = v.len() * 2;
-        for _ in 0..attempts {
-            let random_idx = self.next() as usize % v.len();
-            if i != random_idx {
-                v.swap(i, random_idx);
-            }
-            i = (i + 1) % v.len();
+impl FromSeed for Random {
+    /// Our state only has 32 bits, so mix all 64 bits of the seed into it.
+    /// Otherwise seeds that differ only in their high bits would give the
+    /// same run.
+    fn from_seed(seed: u64) -> Self {
+        Random {
+            seed: splitmix64(seed) % MODULUS,
+        }
+    }
+}
+
+impl Rng for Random {
+    fn next_u64(&mut self) -> u64 {
+        (self.next_u32() << 32) | self.next_u32()
+    }
+}
+
+// https://en.wikipedia.org/wiki/Xorshift#xorshift*
+const XORSHIFT_MULTIPLIER: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// A xorshift64* generator, which is nearly as fast as `Random` but has much
+/// better statistical quality.
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Default for Xorshift {
+    fn default() -> Self {
+        Xorshift::new()
+    }
+}
+
+impl Xorshift {
+    pub fn new() -> Self {
+        Xorshift::from_seed(new_seed())
+    }
+}
+
+impl FromSeed for Xorshift {
+    fn from_seed(seed: u64) -> Self {
+        // The state must never be zero, so we scramble the seed with a
+        // SplitMix64 step, which maps zero to a non-zero value.
+        Xorshift {
+            state: splitmix64(seed).max(1),
         }
     }
 }
+
+impl Rng for Xorshift {
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(XORSHIFT_MULTIPLIER)
+    }
+}
+
+/// Adapts any generator from the `rand` ecosystem, e.g.
+/// `RandCore(rand::rngs::StdRng::seed_from_u64(1))`.
+#[cfg(feature = "rand")]
+pub struct RandCore<R: rand_core::RngCore>(pub R);
+
+#[cfg(feature = "rand")]
+impl<R: rand_core::RngCore> Rng for RandCore<R> {
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<R: rand_core::RngCore + rand_core::SeedableRng> FromSeed for RandCore<R> {
+    fn from_seed(seed: u64) -> Self {
+        RandCore(R::seed_from_u64(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FromSeed, Random, Rng, Xorshift};
+
+    fn assert_shuffle_permutes<R: Rng>(mut rng: R) {
+        let mut v = (0..52).collect::<Vec<_>>();
+        rng.shuffle(&mut v);
+        assert_ne!(v, (0..52).collect::<Vec<_>>());
+        v.sort_unstable();
+        assert_eq!(v, (0..52).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_generators_are_deterministic() {
+        assert_eq!(
+            Random::from_seed(5).next_u64(),
+            Random::from_seed(5).next_u64()
+        );
+        assert_eq!(
+            Xorshift::from_seed(5).next_u64(),
+            Xorshift::from_seed(5).next_u64()
+        );
+        assert_ne!(Xorshift::from_seed(0).next_u64(), 0);
+        // Seeds that only differ in their high bits still give different
+        // runs.
+        assert_ne!(
+            Random::from_seed(5).next_u64(),
+            Random::from_seed(5 + (1 << 32)).next_u64()
+        );
+    }
+
+    #[test]
+    fn test_gen_range_stays_in_range() {
+        let mut rng = Xorshift::from_seed(1);
+        for n in 1..100 {
+            assert!(rng.gen_range(n) < n);
+        }
+    }
+
+    #[test]
+    fn test_gen_range_is_roughly_uniform() {
+        let mut rng = Xorshift::from_seed(1);
+        let mut counts = [0; 3];
+        for _ in 0..30000 {
+            counts[rng.gen_range(3)] += 1;
+        }
+        for count in counts.iter() {
+            assert!(*count > 9500 && *count < 10500, "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_deal_chooses_distinct_items() {
+        let mut rng = Xorshift::from_seed(1);
+        let mut v = (0..52).collect::<Vec<_>>();
+        for _ in 0..100 {
+            let mut dealt = rng.deal(&mut v, 9).to_vec();
+            dealt.sort_unstable();
+            dealt.dedup();
+            assert_eq!(dealt.len(), 9);
+        }
+        v.sort_unstable();
+        assert_eq!(v, (0..52).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_shuffle_permutes() {
+        assert_shuffle_permutes(Random::from_seed(1));
+        assert_shuffle_permutes(Xorshift::from_seed(1));
+    }
+}
@@ -0,0 +1,220 @@
+// https://en.wikipedia.org/wiki/Pearson%27s_chi-squared_test
+use std::collections::HashMap;
+
+use super::random::{FromSeed, Rng};
+
+/// The z-score above which we consider a test to have failed. This
+/// corresponds to a one-sided p-value of about 0.0005, so a correct
+/// shuffler should fail a given test only about once in 2,000 runs.
+pub const DEFAULT_MAX_Z_SCORE: f64 = 3.3;
+
+/// The number of cards in the small deck used to test permutation
+/// uniformity. There are 5! = 120 possible permutations of it.
+const SMALL_DECK_SIZE: usize = 5;
+
+pub struct TestResult {
+    pub name: &'static str,
+    pub chi_square: f64,
+    pub degrees_of_freedom: usize,
+}
+
+impl TestResult {
+    /// Approximates how many standard deviations the chi-square statistic
+    /// lies above its expected value, using the Wilson-Hilferty
+    /// transformation.
+    pub fn z_score(&self) -> f64 {
+        let df = self.degrees_of_freedom as f64;
+        let variance = 2.0 / (9.0 * df);
+        ((self.chi_square / df).cbrt() - (1.0 - variance)) / variance.sqrt()
+    }
+
+    pub fn passed(&self, max_z_score: f64) -> bool {
+        self.z_score() <= max_z_score
+    }
+}
+
+fn chi_square<'a, I: IntoIterator<Item = &'a usize>>(observed: I, expected: f64) -> f64 {
+    observed
+        .into_iter()
+        .map(|&count| {
+            let diff = count as f64 - expected;
+            diff * diff / expected
+        })
+        .sum()
+}
+
+fn new_deck(size: usize) -> Vec<usize> {
+    (0..size).collect()
+}
+
+/// Checks that every card is equally likely to end up in every position.
+pub fn test_position_frequencies<F>(shuffle: &mut F, deck_size: usize, trials: usize) -> TestResult
+where
+    F: FnMut(&mut [usize]),
+{
+    let mut counts = vec![0; deck_size * deck_size];
+
+    for _ in 0..trials {
+        let mut deck = new_deck(deck_size);
+        shuffle(&mut deck);
+        for (position, card) in deck.iter().enumerate() {
+            counts[card * deck_size + position] += 1;
+        }
+    }
+
+    TestResult {
+        name: "Card position frequencies",
+        chi_square: chi_square(&counts, trials as f64 / deck_size as f64),
+        degrees_of_freedom: (deck_size - 1) * (deck_size - 1),
+    }
+}
+
+/// Checks that every card is equally likely to be followed by every other
+/// card.
+pub fn test_pair_adjacency<F>(shuffle: &mut F, deck_size: usize, trials: usize) -> TestResult
+where
+    F: FnMut(&mut [usize]),
+{
+    let mut counts = vec![0; deck_size * deck_size];
+
+    for _ in 0..trials {
+        let mut deck = new_deck(deck_size);
+        shuffle(&mut deck);
+        for pair in deck.windows(2) {
+            counts[pair[0] * deck_size + pair[1]] += 1;
+        }
+    }
+
+    // A card can never follow itself, so we leave those cells out.
+    let observed = counts
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i / deck_size != i % deck_size)
+        .map(|(_, count)| count);
+
+    TestResult {
+        name: "Adjacent pair frequencies",
+        chi_square: chi_square(observed, trials as f64 / deck_size as f64),
+        degrees_of_freedom: deck_size * (deck_size - 1) - 1,
+    }
+}
+
+/// Checks that every permutation of a small deck is equally likely.
+pub fn test_permutation_uniformity<F>(shuffle: &mut F, trials: usize) -> TestResult
+where
+    F: FnMut(&mut [usize]),
+{
+    let mut counts: HashMap<Vec<usize>, usize> = HashMap::new();
+
+    for _ in 0..trials {
+        let mut deck = new_deck(SMALL_DECK_SIZE);
+        shuffle(&mut deck);
+        *counts.entry(deck).or_insert(0) += 1;
+    }
+
+    let num_permutations = (1..=SMALL_DECK_SIZE).product::<usize>();
+    let mut observed = counts.values().copied().collect::<Vec<_>>();
+    observed.resize(num_permutations, 0);
+
+    TestResult {
+        name: "Small deck permutation frequencies",
+        chi_square: chi_square(&observed, trials as f64 / num_permutations as f64),
+        degrees_of_freedom: num_permutations - 1,
+    }
+}
+
+/// Runs every test against the given shuffle implementation, using a
+/// standard 52-card deck where the deck size matters.
+pub fn test_shuffle<F>(mut shuffle: F, trials: usize) -> Vec<TestResult>
+where
+    F: FnMut(&mut [usize]),
+{
+    vec![
+        test_position_frequencies(&mut shuffle, 52, trials),
+        test_pair_adjacency(&mut shuffle, 52, trials),
+        test_permutation_uniformity(&mut shuffle, trials),
+    ]
+}
+
+/// Runs every test against the given generator's shuffle.
+pub fn test_rng<R: Rng + FromSeed>(seed: u64, trials: usize) -> Vec<TestResult> {
+    let mut random = R::from_seed(seed);
+    test_shuffle(|deck| random.shuffle(deck), trials)
+}
+
+/// Prints the results of the tests. Returns whether all of them passed.
+pub fn print_results(results: &[TestResult], seed: u64, trials: usize, max_z_score: f64) -> bool {
+    let mut all_passed = true;
+
+    println!("Random seed:\n  {}", seed);
+    println!();
+    println!("Chi-square tests after shuffling {} times:\n", trials);
+
+    for result in results.iter() {
+        let passed = result.passed(max_z_score);
+        all_passed = all_passed && passed;
+        println!(
+            "  {:36} chi-square {:10.1} (df {:4}), z {:6.2}  {}",
+            result.name,
+            result.chi_square,
+            result.degrees_of_freedom,
+            result.z_score(),
+            if passed { "PASS" } else { "FAIL" }
+        );
+    }
+
+    all_passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::random::{FromSeed, Random, Rng, Xorshift};
+    use super::{test_shuffle, DEFAULT_MAX_Z_SCORE};
+
+    const TRIALS: usize = 20_000;
+
+    fn assert_all_pass<F: FnMut(&mut [usize])>(shuffle: F) {
+        for result in test_shuffle(shuffle, TRIALS) {
+            assert!(
+                result.passed(DEFAULT_MAX_Z_SCORE),
+                "{} failed with z = {}",
+                result.name,
+                result.z_score()
+            );
+        }
+    }
+
+    #[test]
+    fn test_generators_pass() {
+        let mut random = Random::from_seed(1);
+        assert_all_pass(|deck| random.shuffle(deck));
+
+        let mut random = Xorshift::from_seed(1);
+        assert_all_pass(|deck| random.shuffle(deck));
+    }
+
+    #[test]
+    fn test_biased_shuffle_fails() {
+        // This is the classic mistake of swapping every position with any
+        // position, rather than only with positions that haven't been
+        // visited yet.
+        let mut random = Xorshift::from_seed(1);
+        let biased_shuffle = |deck: &mut [usize]| {
+            for i in 0..deck.len() {
+                let j = random.gen_range(deck.len());
+                deck.swap(i, j);
+            }
+        };
+
+        assert!(test_shuffle(biased_shuffle, TRIALS)
+            .iter()
+            .any(|result| !result.passed(DEFAULT_MAX_Z_SCORE)));
+    }
+
+    #[test]
+    fn test_identity_shuffle_fails() {
+        for result in test_shuffle(|_| {}, 1000) {
+            assert!(!result.passed(DEFAULT_MAX_Z_SCORE), "{}", result.name);
+        }
+    }
+}
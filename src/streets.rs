@@ -0,0 +1,232 @@
+//! Shows how our equity develops as the community cards are dealt, rather
+//! than only how the hand ends up on the river.
+use std::cmp::Ordering;
+
+use super::card::Card;
+use super::hand::Hand;
+use super::random::Rng;
+use super::texas::{remove_from_deck, simulate, Counters, NUM_HOLE_CARDS};
+
+pub const AHEAD: &str = "Ahead";
+pub const TIED: &str = "Tied";
+pub const BEHIND: &str = "Behind";
+pub const POSITIONS: [&str; 3] = [AHEAD, TIED, BEHIND];
+
+/// The name of each street, along with how many community cards have been
+/// dealt by then.
+const STREETS: [(&str, usize); 4] = [("Preflop", 0), ("Flop", 3), ("Turn", 4), ("River", 5)];
+
+/// The percentiles of equity we report for each street.
+pub const PERCENTILES: [usize; 5] = [10, 25, 50, 75, 90];
+
+/// Our equity against one random opponent across the boards that could be
+/// showing on a street.
+pub struct Street {
+    pub name: &'static str,
+    pub num_community_cards: usize,
+    /// How many games we played on each sampled board to estimate our
+    /// equity there.
+    pub games_per_board: usize,
+    /// Our estimated equity on each sampled board, in increasing order.
+    pub equities: Vec<f64>,
+    /// Whether our best hand so far is ahead of, tied with or behind a
+    /// random opponent's on each sampled board.
+    pub positions: Counters,
+}
+
+impl Street {
+    pub fn mean_equity(&self) -> f64 {
+        self.equities.iter().sum::<f64>() / self.equities.len() as f64
+    }
+
+    /// Returns the given percentile of our equity across boards, using the
+    /// nearest rank.
+    pub fn percentile(&self, percentile: usize) -> f64 {
+        let index = (percentile * (self.equities.len() - 1) + 50) / 100;
+        self.equities[index]
+    }
+
+    /// Returns the share of boards on which we are the favourite.
+    pub fn favourite_probability(&self) -> f64 {
+        self.equities.iter().filter(|&&equity| equity > 0.5).count() as f64
+            / self.equities.len() as f64
+    }
+}
+
+fn equity<R: Rng>(hole_cards: &[Card], board: &[Card], num_games: usize, random: &mut R) -> f64 {
+    simulate(hole_cards, board, num_games, random).equity().mean
+}
+
+/// Samples boards for every street from the given one to the river. On each
+/// board we estimate our equity by playing `games_per_board` games against a
+/// random opponent, and check whether our hand so far is ahead of a random
+/// opponent's. The street we are already on has only one board, so all of
+/// its games go towards a single estimate.
+pub fn progression<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_boards: usize,
+    games_per_board: usize,
+    random: &mut R,
+) -> Vec<Street> {
+    progression_with(
+        hole_cards,
+        board,
+        num_boards,
+        games_per_board,
+        random,
+        || false,
+    )
+    .expect("the progression is never stopped")
+}
+
+/// Like `progression`, but checks `is_stopped` before each board, and gives
+/// up with `None` once it returns true.
+pub fn progression_with<R: Rng, S: Fn() -> bool>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_boards: usize,
+    games_per_board: usize,
+    random: &mut R,
+    is_stopped: S,
+) -> Option<Vec<Street>> {
+    assert_eq!(hole_cards.len(), NUM_HOLE_CARDS, "Must have 2 hole cards");
+
+    let mut deck = remove_from_deck(Card::new_deck(), [board, hole_cards].concat());
+
+    STREETS
+        .iter()
+        .filter(|(_, num_community_cards)| *num_community_cards >= board.len())
+        .map(|&(name, num_community_cards)| {
+            let num_cards_to_draw = num_community_cards - board.len();
+            let mut positions = Counters::default();
+            let mut equities = vec![];
+
+            for _ in 0..num_boards {
+                if is_stopped() {
+                    return None;
+                }
+                let dealt = random
+                    .deal(&mut deck, NUM_HOLE_CARDS + num_cards_to_draw)
+                    .to_vec();
+                let (opponent_hole_cards, drawn_cards) = dealt.split_at(NUM_HOLE_CARDS);
+                let street_board = [board, drawn_cards].concat();
+
+                let hand = Hand::from([street_board.as_slice(), hole_cards].concat());
+                let opponent_hand =
+                    Hand::from([street_board.as_slice(), opponent_hole_cards].concat());
+                positions.increment(match hand.cmp(&opponent_hand) {
+                    Ordering::Greater => AHEAD,
+                    Ordering::Equal => TIED,
+                    Ordering::Less => BEHIND,
+                });
+
+                if num_cards_to_draw > 0 {
+                    equities.push(equity(hole_cards, &street_board, games_per_board, random));
+                }
+            }
+
+            let games_per_board = if num_cards_to_draw == 0 {
+                if is_stopped() {
+                    return None;
+                }
+                let num_games = num_boards * games_per_board;
+                equities.push(equity(hole_cards, board, num_games, random));
+                num_games
+            } else {
+                games_per_board
+            };
+            equities.sort_by(|a, b| a.partial_cmp(b).expect("equities are never NaN"));
+
+            Some(Street {
+                name,
+                num_community_cards,
+                games_per_board,
+                equities,
+                positions,
+            })
+        })
+        .collect()
+}
+
+pub fn print_progression(streets: &[Street]) {
+    println!("Equity against one random opponent on each street, across the sampled boards:\n");
+
+    print!(
+        "  {:8} {:>6} {:>11} {:>7}",
+        "", "Boards", "Games/board", "Mean"
+    );
+    for percentile in PERCENTILES.iter() {
+        print!(" {:>6}", format!("p{}", percentile));
+    }
+    println!(" {:>9} {:>7} {:>7} {:>7}", "Favourite", AHEAD, TIED, BEHIND);
+
+    for street in streets.iter() {
+        print!(
+            "  {:8} {:>6} {:>11} {:>6.1}%",
+            street.name,
+            street.equities.len(),
+            street.games_per_board,
+            street.mean_equity() * 100.0
+        );
+        for &percentile in PERCENTILES.iter() {
+            print!(" {:>5.1}%", street.percentile(percentile) * 100.0);
+        }
+        let total = street.positions.values().sum::<usize>() as f64;
+        print!(" {:>8.1}%", street.favourite_probability() * 100.0);
+        for position in POSITIONS.iter() {
+            let count = street.positions.get(position).copied().unwrap_or(0);
+            print!(" {:>6.1}%", count as f64 / total * 100.0);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::{progression, progression_with};
+
+    #[test]
+    fn test_progression_covers_remaining_streets() {
+        let hole_cards = Card::try_vec_from("as ad").unwrap();
+        let board = Card::try_vec_from("kh 7c 2d").unwrap();
+        let streets = progression(&hole_cards, &board, 20, 20, &mut Xorshift::from_seed(1));
+
+        let names = streets.iter().map(|street| street.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Flop", "Turn", "River"]);
+
+        // The flop is already known, so it has a single estimate.
+        assert_eq!(streets[0].equities.len(), 1);
+        assert_eq!(streets[1].equities.len(), 20);
+        for street in streets.iter() {
+            assert_eq!(street.positions.values().sum::<usize>(), 20);
+            assert!(street.percentile(10) <= street.percentile(90));
+            assert!(street.mean_equity() > 0.5);
+        }
+    }
+
+    #[test]
+    fn test_progression_gives_up_when_stopped() {
+        let hole_cards = Card::try_vec_from("as ad").unwrap();
+        let board = Card::try_vec_from("kh 7c 2d").unwrap();
+        // Stop partway through the turn's boards.
+        let checks = Cell::new(0);
+        let streets = progression_with(
+            &hole_cards,
+            &board,
+            20,
+            20,
+            &mut Xorshift::from_seed(1),
+            || {
+                checks.set(checks.get() + 1);
+                checks.get() > 30
+            },
+        );
+        assert!(streets.is_none());
+        assert_eq!(checks.get(), 31);
+    }
+}
@@ -0,0 +1,283 @@
+//! Measures how strong our hand is right now by comparing it with every two
+//! cards an opponent could hold, rather than by simulating.
+use std::cmp::Ordering;
+
+use super::card::Card;
+use super::combinations::Combinations;
+use super::hand::Hand;
+use super::texas::{remove_from_deck, NUM_COMMUNITY_CARDS, NUM_HOLE_CARDS};
+
+/// A hand that can be made on the board, along with how many holdings make
+/// it.
+#[derive(Clone)]
+pub struct HandClass {
+    pub description: String,
+    /// One of the holdings that makes the hand.
+    pub example: Vec<Card>,
+    /// How many holdings make the hand, including ones using our cards.
+    pub num_combos: usize,
+    /// How many of those holdings an opponent could have.
+    pub num_opponent_combos: usize,
+}
+
+pub struct Strength {
+    pub description: String,
+    /// How many two-card holdings an opponent could have.
+    pub num_combos: usize,
+    pub beaten_by: usize,
+    pub tied_by: usize,
+    pub beats: usize,
+    /// Every hand that is stronger than ours, strongest first. The first is
+    /// the nuts, unless we have them.
+    pub stronger_hands: Vec<HandClass>,
+    /// The nuts, i.e. the strongest hand that any holding can make.
+    pub nuts: HandClass,
+}
+
+impl Strength {
+    /// Our position among the hands that can be made, where 1 means we have
+    /// the nuts and 2 means the second nuts.
+    pub fn nuts_rank(&self) -> usize {
+        self.stronger_hands.len() + 1
+    }
+
+    /// The share of opponent holdings we beat, counting a tie as half.
+    pub fn percentile(&self) -> f64 {
+        (self.beats as f64 + self.tied_by as f64 / 2.0) / self.num_combos as f64
+    }
+}
+
+/// Returns "1st", "2nd", "3rd", "4th" and so on.
+pub fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Compares our hand with every holding that could be dealt alongside the
+/// board. To find the nuts we also consider holdings that use our own
+/// cards, since they are stronger hands that could have been made on this
+/// board even if an opponent can't make them now.
+pub fn hand_strength(hole_cards: &[Card], board: &[Card]) -> Strength {
+    assert_eq!(hole_cards.len(), NUM_HOLE_CARDS, "Must have 2 hole cards");
+    assert!(
+        board.len() >= 3 && board.len() <= NUM_COMMUNITY_CARDS,
+        "Must have a flop, turn or river"
+    );
+
+    let hand = Hand::from([board, hole_cards].concat());
+    let deck = remove_from_deck(Card::new_deck(), board.to_vec());
+    let mut holdings = Combinations::new(&deck, NUM_HOLE_CARDS)
+        .map(|holding| {
+            let holding_hand = Hand::from([board, &holding].concat());
+            (holding, holding_hand)
+        })
+        .collect::<Vec<_>>();
+    holdings.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+    let mut num_combos = 0;
+    let mut beaten_by = 0;
+    let mut tied_by = 0;
+    let mut classes: Vec<(HandClass, Ordering)> = vec![];
+
+    for (holding, holding_hand) in holdings.iter() {
+        let is_opponents = !holding.iter().any(|card| hole_cards.contains(card));
+        let cmp = holding_hand.cmp(&hand);
+        if is_opponents {
+            num_combos += 1;
+            match cmp {
+                Ordering::Greater => beaten_by += 1,
+                Ordering::Equal => tied_by += 1,
+                Ordering::Less => {}
+            }
+        }
+
+        let is_new_class = match classes.last() {
+            Some((class, _)) => {
+                let class_hand = Hand::from([board, &class.example].concat());
+                holding_hand.cmp(&class_hand) != Ordering::Equal
+            }
+            None => true,
+        };
+        if is_new_class {
+            let category = holding_hand
+                .find_best_category()
+                .expect("a hand with hole cards is non-empty");
+            classes.push((
+                HandClass {
+                    description: category.describe(holding_hand),
+                    example: holding.clone(),
+                    num_combos: 0,
+                    num_opponent_combos: 0,
+                },
+                cmp,
+            ));
+        }
+        let (class, _) = classes.last_mut().expect("we just made sure of it");
+        class.num_combos += 1;
+        if is_opponents {
+            class.num_opponent_combos += 1;
+        }
+    }
+
+    let mut classes = classes.into_iter();
+    let (nuts, nuts_cmp) = classes.next().expect("there is always a holding");
+    let mut stronger_hands = classes
+        .take_while(|(_, cmp)| *cmp == Ordering::Greater)
+        .map(|(class, _)| class)
+        .collect::<Vec<_>>();
+    let category = hand
+        .find_best_category()
+        .expect("a hand with hole cards is non-empty");
+    let description = category.describe(&hand);
+
+    if nuts_cmp == Ordering::Greater {
+        stronger_hands.insert(0, nuts.clone());
+    }
+
+    Strength {
+        description,
+        num_combos,
+        beaten_by,
+        tied_by,
+        beats: num_combos - beaten_by - tied_by,
+        stronger_hands,
+        nuts,
+    }
+}
+
+/// The most stronger hands we list before summarizing the rest.
+const MAX_STRONGER_HANDS_SHOWN: usize = 10;
+
+pub fn print_strength(hole_cards: &[Card], board: &[Card], strength: &Strength) {
+    println!("Hole cards:\n  {}", Card::vec_to_string(hole_cards));
+    println!("Community cards:\n  {}", Card::vec_to_string(board));
+    println!("Current hand:\n  {}", strength.description);
+    println!();
+
+    println!(
+        "Against all {} two-card holdings an opponent could have:\n",
+        strength.num_combos
+    );
+    for (label, count) in [
+        ("Beat us", strength.beaten_by),
+        ("Tie us", strength.tied_by),
+        ("Lose to us", strength.beats),
+    ]
+    .iter()
+    {
+        println!(
+            "  {:12} {:>5} {:>6.1}%",
+            label,
+            count,
+            *count as f64 / strength.num_combos as f64 * 100.0
+        );
+    }
+    println!();
+
+    println!(
+        "Hand strength percentile:\n  {:.1}%",
+        strength.percentile() * 100.0
+    );
+    println!(
+        "Rank:\n  {}, beaten by {} of {} combos",
+        if strength.nuts_rank() == 1 {
+            String::from("The nuts")
+        } else {
+            format!("{} nuts", ordinal(strength.nuts_rank()))
+        },
+        strength.beaten_by,
+        strength.num_combos
+    );
+    println!(
+        "The nuts:\n  {}, e.g. with {} ({} combos)",
+        strength.nuts.description,
+        Card::vec_to_string(&strength.nuts.example),
+        strength.nuts.num_combos
+    );
+
+    if !strength.stronger_hands.is_empty() {
+        println!();
+        println!("Stronger hands, with how many combos an opponent could have:\n");
+        for class in strength
+            .stronger_hands
+            .iter()
+            .take(MAX_STRONGER_HANDS_SHOWN)
+        {
+            println!(
+                "  {:48} {:>4}",
+                class.description, class.num_opponent_combos
+            );
+        }
+        if strength.stronger_hands.len() > MAX_STRONGER_HANDS_SHOWN {
+            println!(
+                "  ...and {} more",
+                strength.stronger_hands.len() - MAX_STRONGER_HANDS_SHOWN
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::card::Card;
+    use super::{hand_strength, ordinal, Strength};
+
+    fn strength(hole_cards: &str, board: &str) -> Strength {
+        hand_strength(
+            &Card::try_vec_from(hole_cards).unwrap(),
+            &Card::try_vec_from(board).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_ordinal_works() {
+        assert_eq!(ordinal(1), "1st");
+        assert_eq!(ordinal(2), "2nd");
+        assert_eq!(ordinal(3), "3rd");
+        assert_eq!(ordinal(4), "4th");
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(22), "22nd");
+    }
+
+    #[test]
+    fn test_counts_every_opponent_combo() {
+        let strength = strength("as kd", "7c 8d 2h");
+        assert_eq!(strength.num_combos, 1081);
+        assert_eq!(strength.beaten_by + strength.tied_by + strength.beats, 1081);
+    }
+
+    #[test]
+    fn test_nut_flush_is_the_nuts() {
+        let strength = strength("as ks", "qs js 2s");
+        assert_eq!(strength.nuts_rank(), 1);
+        assert_eq!(strength.beaten_by, 0);
+        assert_eq!(strength.nuts.description, "Ace-high flush");
+        assert_eq!(strength.nuts.num_opponent_combos, 0);
+    }
+
+    #[test]
+    fn test_board_royal_flush_ties_everyone() {
+        let strength = strength("2c 3d", "as ks qs js 10s");
+        assert_eq!(strength.nuts_rank(), 1);
+        assert_eq!(strength.tied_by, strength.num_combos);
+        assert_eq!(strength.percentile(), 0.5);
+    }
+
+    #[test]
+    fn test_second_nuts() {
+        // Only pocket nines, for a set, beat our set of sevens.
+        let strength = strength("7h 7d", "9c 7s 2h");
+        assert_eq!(strength.nuts_rank(), 2);
+        assert_eq!(strength.beaten_by, 3);
+        assert_eq!(
+            strength.nuts.description,
+            "Three of a kind, Nines, Seven-Two kickers"
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::ops::Deref;
+use std::ops::{ControlFlow, Deref};
+use std::thread;
 
 use super::card::Card;
 use super::hand::{Category, Hand};
-use super::random::Random;
+use super::preflop::StartingHand;
+use super::random::{derive_seed, FromSeed, Rng};
+use super::variance::Estimate;
 
-struct Counters(HashMap<&'static str, usize>);
+#[derive(Clone, Default)]
+pub struct Counters(HashMap<&'static str, usize>);
 
 impl Deref for Counters {
     type Target = HashMap<&'static str, usize>;
@@ -22,35 +26,162 @@ impl Counters {
         *counter += 1;
     }
 
-    pub fn print_percentages(&self) {
-        let mut entries = self.0.clone().into_iter().collect::<Vec<_>>();
-        let total = entries.iter().fold(0, |acc, (_, value)| acc + value) as f64;
+    /// Adds all of the other counters' counts to ours.
+    pub fn merge(&mut self, other: &Counters) {
+        for (key, count) in other.iter() {
+            *self.0.entry(key).or_insert(0) += count;
+        }
+    }
+
+    /// Prints the count and percentage of each of the given keys in order,
+    /// including ones that never came up, along with a bar proportional to
+    /// the percentage.
+    pub fn print_percentages(&self, keys: &[&str]) {
+        let total = self.values().sum::<usize>();
+        let count_width = total.to_string().len();
+
+        for key in keys.iter() {
+            let count = self.get(key).copied().unwrap_or(0);
+            let fraction = if total == 0 {
+                0.0
+            } else {
+                count as f64 / total as f64
+            };
+            let line = format!(
+                "  {:20} {:>5.1}% {:>width$}  {}",
+                key,
+                fraction * 100.0,
+                count,
+                bar(fraction),
+                width = count_width
+            );
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+/// The width of a bar representing 100%.
+const BAR_WIDTH: usize = 40;
+
+/// Draws a bar whose length is proportional to the given fraction. Any
+/// fraction above zero gets at least a dot, so rare keys stand out from ones
+/// that never came up.
+fn bar(fraction: f64) -> String {
+    let width = (fraction * BAR_WIDTH as f64).round() as usize;
+    if width == 0 && fraction > 0.0 {
+        String::from(".")
+    } else {
+        "#".repeat(width)
+    }
+}
+
+/// Counts pairs of keys, keeping a separate set of counters for each row key
+/// so that we can see how the column keys are distributed given the row key.
+#[derive(Clone, Default)]
+pub struct CrossTab(HashMap<&'static str, Counters>);
+
+impl Deref for CrossTab {
+    type Target = HashMap<&'static str, Counters>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl CrossTab {
+    pub fn increment(&mut self, row: &'static str, column: &'static str) {
+        self.0.entry(row).or_default().increment(column);
+    }
+
+    /// Adds all of the other table's counts to ours.
+    pub fn merge(&mut self, other: &CrossTab) {
+        for (row, counters) in other.iter() {
+            self.0.entry(row).or_default().merge(counters);
+        }
+    }
+
+    /// Prints how often each row came up, followed by the percentage of
+    /// that row's games that fell into each column. Rows that never came up
+    /// are left out.
+    pub fn print_percentages(&self, rows: &[&str], columns: &[&str], column_labels: &[&str]) {
+        let total = self
+            .values()
+            .map(|counters| counters.values().sum::<usize>())
+            .sum::<usize>() as f64;
 
-        entries.sort();
+        print!("  {:20} {:>8}", "", "Share");
+        for label in column_labels.iter() {
+            print!(" {:>8}", label);
+        }
+        println!();
 
-        for (key, count) in entries {
-            let pct = count as f64 / total * 100.0;
-            println!("  {:20} {:.1}%", key, pct);
+        for row in rows.iter() {
+            let counters = match self.get(row) {
+                Some(counters) => counters,
+                None => continue,
+            };
+            let row_total = counters.values().sum::<usize>() as f64;
+            print!("  {:20} {:>7.1}%", row, row_total / total * 100.0);
+            for column in columns.iter() {
+                let count = counters.get(column).copied().unwrap_or(0);
+                print!(" {:>7.1}%", count as f64 / row_total * 100.0);
+            }
+            println!();
         }
     }
 }
 
-fn category_to_str(cat: &Category) -> &'static str {
-    match cat {
-        Category::HighCard(_) => "High card",
-        Category::OnePair(_) => "One pair",
-        Category::TwoPair(_, _) => "Two pair",
-        Category::ThreeOfAKind(_) => "Three of a kind",
-        Category::Straight(_) => "Straight",
-        Category::Flush(_, _, _, _, _) => "Flush",
-        Category::FullHouse(_, _) => "Full house",
-        Category::FourOfAKind(_) => "Four of a kind",
-        Category::StraightFlush(_) => "Straight flush",
+/// Short forms of each category's name, indexed by `Category::rank()`, for
+/// use as column headings.
+const CATEGORY_ABBREVIATIONS: [&str; 9] = [
+    "High", "Pair", "2 pair", "Trips", "Straight", "Flush", "Full", "Quads", "Str fl",
+];
+
+/// The tallies from simulating a number of games.
+#[derive(Clone, Default)]
+pub struct SimulationResults {
+    pub num_iterations: usize,
+    pub hand_categories: Counters,
+    pub opponent_hand_categories: Counters,
+    pub game_outcomes: Counters,
+    /// Outcomes, broken down by our hand's category.
+    pub outcomes_by_category: CrossTab,
+    /// The opponent's hand category, broken down by our hand's category.
+    pub opponent_categories_by_category: CrossTab,
+    /// The total share of the pot we won, where a pot split k ways counts as
+    /// 1/k of it, along with the total of the squared shares.
+    pub pot_shares: f64,
+    pub squared_pot_shares: f64,
+}
+
+impl SimulationResults {
+    pub fn merge(&mut self, other: &SimulationResults) {
+        self.num_iterations += other.num_iterations;
+        self.hand_categories.merge(&other.hand_categories);
+        self.opponent_hand_categories
+            .merge(&other.opponent_hand_categories);
+        self.game_outcomes.merge(&other.game_outcomes);
+        self.outcomes_by_category.merge(&other.outcomes_by_category);
+        self.opponent_categories_by_category
+            .merge(&other.opponent_categories_by_category);
+        self.pot_shares += other.pot_shares;
+        self.squared_pot_shares += other.squared_pot_shares;
+    }
+
+    /// Returns our pot equity, i.e. the average share of the pot we won.
+    pub fn equity(&self) -> Estimate {
+        let n = self.num_iterations.max(1) as f64;
+        let mean = self.pot_shares / n;
+        Estimate {
+            mean,
+            variance: (self.squared_pot_shares / n - mean * mean).max(0.0) / n,
+            samples: self.num_iterations,
+        }
     }
 }
 
-fn remove_from_deck(mut deck: Vec<Card>, cards: Vec<Card>) -> Vec<Card> {
-    deck.retain(|card| !cards.contains(&card));
+pub fn remove_from_deck(mut deck: Vec<Card>, cards: Vec<Card>) -> Vec<Card> {
+    deck.retain(|card| !cards.contains(card));
     deck
 }
 
@@ -58,90 +189,444 @@ pub const NUM_COMMUNITY_CARDS: usize = 5;
 pub const NUM_HOLE_CARDS: usize = 2;
 pub const NUM_TOTAL_CARDS: usize = NUM_COMMUNITY_CARDS + NUM_HOLE_CARDS;
 
-pub fn run_texas_hold_em(
-    hole_cards: Vec<Card>,
-    src_community_cards: Vec<Card>,
-    num_iterations: usize,
-    mut random: Random,
-) {
-    let mut hand_categories = Counters(HashMap::new());
-    let mut opponent_hand_categories = Counters(HashMap::new());
-    let mut game_outcomes = Counters(HashMap::new());
-    let orig_deck = remove_from_deck(
-        Card::new_deck(),
-        [src_community_cards.clone(), hole_cards.clone()].concat(),
-    );
+/// The most opponents we can play against, making a full table of ten.
+pub const MAX_OPPONENTS: usize = 9;
 
-    println!("Hole cards:\n  {}", Card::vec_to_string(&hole_cards),);
+pub const WIN: &str = "Win";
+pub const TIE: &str = "Tie";
+pub const LOSS: &str = "Loss";
+pub const OUTCOMES: [&str; 3] = [WIN, TIE, LOSS];
 
-    if !src_community_cards.is_empty() {
-        println!(
-            "Community cards:\n  {}",
-            Card::vec_to_string(&src_community_cards)
-        );
-    }
+/// A single simulated game.
+pub struct Deal<'a> {
+    /// All five community cards, including the ones we were given.
+    pub community_cards: &'a [Card],
+    /// The hole cards of every opponent, two at a time.
+    pub opponent_hole_cards: &'a [Card],
+    pub category: Category,
+    /// The category of the best opponent hand.
+    pub opponent_category: Category,
+    /// `WIN` if we beat every opponent, `TIE` if we split the pot with the
+    /// best of them, and `LOSS` otherwise.
+    pub outcome: &'static str,
+    /// Our share of the pot, e.g. 1/3 when splitting it three ways.
+    pub pot_share: f64,
+}
 
-    println!();
+/// Plays the given number of games against one opponent, randomly dealing
+/// the opponent's hole cards and any missing community cards each time.
+pub fn simulate<R: Rng>(
+    hole_cards: &[Card],
+    src_community_cards: &[Card],
+    num_iterations: usize,
+    random: &mut R,
+) -> SimulationResults {
+    simulate_with(
+        hole_cards,
+        src_community_cards,
+        1,
+        num_iterations,
+        random,
+        |_| ControlFlow::Continue(()),
+    )
+}
 
+/// Like `simulate`, but plays against the given number of opponents and
+/// passes every game to `on_deal` as it is played. If `on_deal` breaks, we
+/// stop early and only report the games played so far.
+pub fn simulate_with<R: Rng, F: FnMut(&Deal) -> ControlFlow<()>>(
+    hole_cards: &[Card],
+    src_community_cards: &[Card],
+    num_opponents: usize,
+    num_iterations: usize,
+    random: &mut R,
+    mut on_deal: F,
+) -> SimulationResults {
     assert_eq!(hole_cards.len(), NUM_HOLE_CARDS, "Must have 2 hole cards");
 
+    assert!(
+        (1..=MAX_OPPONENTS).contains(&num_opponents),
+        "Must have between 1 and 9 opponents"
+    );
+
     assert!(
         src_community_cards.len() <= NUM_COMMUNITY_CARDS,
         "Must have at most 5 community cards"
     );
 
+    let mut results = SimulationResults::default();
+    let mut deck = remove_from_deck(Card::new_deck(), [src_community_cards, hole_cards].concat());
     let num_cards_to_draw = NUM_COMMUNITY_CARDS - src_community_cards.len();
 
+    // Dealing leaves the deck as a permutation of the same cards, so we can
+    // keep dealing from it without resetting it between iterations.
     for _ in 0..num_iterations {
-        let mut deck = orig_deck.clone();
-        let mut community_cards = src_community_cards.clone();
-        let mut opponent_hole_cards = vec![];
-        random.shuffle(&mut deck);
-
-        for _ in 0..NUM_COMMUNITY_CARDS {
-            opponent_hole_cards.push(deck.pop().unwrap());
-        }
-
-        for _ in 0..num_cards_to_draw {
-            community_cards.push(deck.pop().unwrap());
-        }
+        let dealt = random.deal(
+            &mut deck,
+            NUM_HOLE_CARDS * num_opponents + num_cards_to_draw,
+        );
+        let (opponent_hole_cards, drawn_cards) = dealt.split_at(NUM_HOLE_CARDS * num_opponents);
+        let community_cards = [src_community_cards, drawn_cards].concat();
 
-        let opponent_hand = Hand::from([community_cards.clone(), opponent_hole_cards].concat());
+        let opponent_hands = opponent_hole_cards
+            .chunks(NUM_HOLE_CARDS)
+            .map(|cards| Hand::from([community_cards.as_slice(), cards].concat()))
+            .collect::<Vec<_>>();
+        let opponent_hand = opponent_hands
+            .iter()
+            .max()
+            .expect("we have at least one opponent");
         let opponent_cat = opponent_hand.find_best_category().unwrap();
-        let hand = Hand::from([community_cards, hole_cards.clone()].concat());
+        let hand = Hand::from([community_cards.as_slice(), hole_cards].concat());
         let cat = hand.find_best_category().unwrap();
 
-        game_outcomes.increment(match hand.cmp(&opponent_hand) {
-            Ordering::Equal => "Tie",
-            Ordering::Greater => "Win",
-            Ordering::Less => "Loss",
+        let (outcome, pot_share) = match hand.cmp(opponent_hand) {
+            Ordering::Equal => {
+                let num_tied = opponent_hands
+                    .iter()
+                    .filter(|h| (*h).cmp(&hand) == Ordering::Equal)
+                    .count();
+                (TIE, 1.0 / (num_tied + 1) as f64)
+            }
+            Ordering::Greater => (WIN, 1.0),
+            Ordering::Less => (LOSS, 0.0),
+        };
+
+        let flow = on_deal(&Deal {
+            community_cards: &community_cards,
+            opponent_hole_cards,
+            category: cat,
+            opponent_category: opponent_cat,
+            outcome,
+            pot_share,
         });
-        opponent_hand_categories.increment(category_to_str(&opponent_cat));
-        hand_categories.increment(category_to_str(&cat));
+
+        results.num_iterations += 1;
+        results.pot_shares += pot_share;
+        results.squared_pot_shares += pot_share * pot_share;
+        results.game_outcomes.increment(outcome);
+        results
+            .opponent_hand_categories
+            .increment(opponent_cat.name());
+        results.hand_categories.increment(cat.name());
+        results.outcomes_by_category.increment(cat.name(), outcome);
+        results
+            .opponent_categories_by_category
+            .increment(cat.name(), opponent_cat.name());
+
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    results
+}
+
+/// Splits the games to simulate across the given number of threads and
+/// merges their results. Each thread gets its own random number generator,
+/// seeded from the master seed and the thread's index, so the results only
+/// depend on the seed and the number of threads.
+pub fn simulate_in_parallel<R: Rng + FromSeed>(
+    hole_cards: &[Card],
+    community_cards: &[Card],
+    num_iterations: usize,
+    seed: u64,
+    num_threads: usize,
+) -> SimulationResults {
+    simulate_in_parallel_with::<R, _, _>(
+        hole_cards,
+        community_cards,
+        1,
+        num_iterations,
+        seed,
+        num_threads,
+        &|| |_: &Deal| ControlFlow::Continue(()),
+    )
+}
+
+/// Like `simulate_in_parallel`, but plays against the given number of
+/// opponents and passes every game to an `on_deal` as it is played. Each
+/// thread gets its own `on_deal` from `new_on_deal`, so that it can keep
+/// count of its games without contending with the other threads. Games from
+/// different threads are passed in no particular order. If a thread's
+/// `on_deal` breaks, that thread stops early.
+pub fn simulate_in_parallel_with<R, N, F>(
+    hole_cards: &[Card],
+    community_cards: &[Card],
+    num_opponents: usize,
+    num_iterations: usize,
+    seed: u64,
+    num_threads: usize,
+    new_on_deal: &N,
+) -> SimulationResults
+where
+    R: Rng + FromSeed,
+    N: Fn() -> F + Sync,
+    F: FnMut(&Deal) -> ControlFlow<()>,
+{
+    assert!(num_threads > 0, "Must have at least one thread");
+
+    let thread_results = thread::scope(|scope| {
+        let handles = (0..num_threads)
+            .map(|i| {
+                // Spread any remainder across the first few threads.
+                let iterations =
+                    num_iterations / num_threads + usize::from(i < num_iterations % num_threads);
+                scope.spawn(move || {
+                    let mut random = R::from_seed(derive_seed(seed, i as u64));
+                    simulate_with(
+                        hole_cards,
+                        community_cards,
+                        num_opponents,
+                        iterations,
+                        &mut random,
+                        new_on_deal(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Simulation thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    let mut results = SimulationResults::default();
+    for thread_result in thread_results.iter() {
+        results.merge(thread_result);
+    }
+    results
+}
+
+/// Prints the cards we are playing with, before simulating any games.
+pub fn print_header(
+    hole_cards: &[Card],
+    src_community_cards: &[Card],
+    seed: u64,
+    num_threads: usize,
+) {
+    println!("Hole cards:\n  {}", Card::vec_to_string(hole_cards),);
+
+    let starting_hand = StartingHand::new(hole_cards[0], hole_cards[1]);
+    let group = match starting_hand.sklansky_group() {
+        Some(group) => format!("Sklansky group {}", group),
+        None => String::from("no Sklansky group"),
+    };
+    println!(
+        "Starting hand:\n  {} ({}, Chen score {}, {:.1}% vs. random, ranked {} of 169)",
+        starting_hand,
+        group,
+        starting_hand.chen_score(),
+        starting_hand.equity_vs_random() * 100.0,
+        starting_hand.equity_rank()
+    );
+
+    if !src_community_cards.is_empty() {
+        println!(
+            "Community cards:\n  {}",
+            Card::vec_to_string(src_community_cards)
+        );
+    }
+
+    println!(
+        "Random seed:\n  {} (split across {} thread{})",
+        seed,
+        num_threads,
+        if num_threads == 1 { "" } else { "s" }
+    );
+
+    println!();
+}
+
+/// Prints the tallies from simulating games, noting if we stopped before
+/// playing all of the games we meant to.
+pub fn print_results(
+    results: &SimulationResults,
+    src_community_cards: &[Card],
+    num_opponents: usize,
+    num_iterations: usize,
+) {
+    let num_cards_to_draw = NUM_COMMUNITY_CARDS - src_community_cards.len();
+    let (opponents, opponent_hand) = if num_opponents == 1 {
+        (String::from("one opponent"), "Opponent hand")
+    } else {
+        (format!("{} opponents", num_opponents), "Best opponent hand")
+    };
+
+    if results.num_iterations < num_iterations {
+        println!(
+            "Stopped early after {} of {} games; these are the results so far.\n",
+            results.num_iterations, num_iterations
+        );
     }
 
     println!(
         "Hand distribution after randomly drawing {} community cards {} times:\n",
-        num_cards_to_draw, num_iterations
+        num_cards_to_draw, results.num_iterations
+    );
+
+    let category_names = Category::kinds()
+        .iter()
+        .map(Category::name)
+        .collect::<Vec<_>>();
+    results.hand_categories.print_percentages(&category_names);
+
+    println!();
+
+    println!(
+        "{} distribution after randomly drawing {} community cards {} times:\n",
+        opponent_hand, num_cards_to_draw, results.num_iterations
     );
 
-    hand_categories.print_percentages();
+    results
+        .opponent_hand_categories
+        .print_percentages(&category_names);
 
     println!();
 
     println!(
-        "Opponent hand distribution after randomly drawing {} community cards {} times:\n",
-        num_cards_to_draw, num_iterations
+        "Outcome distribution after playing against {} {} times:\n",
+        opponents, results.num_iterations
     );
 
-    opponent_hand_categories.print_percentages();
+    results.game_outcomes.print_percentages(&OUTCOMES);
 
     println!();
 
+    let equity = results.equity();
     println!(
-        "Outcome distribution after playing against one opponent {} times:\n",
-        num_iterations
+        "Pot equity, counting a pot split k ways as 1/k of it:\n\n  {:.2}% ± {:.2}%",
+        equity.mean * 100.0,
+        equity.std_error() * 100.0
+    );
+
+    println!();
+
+    println!("Outcomes given our hand category:\n");
+
+    results
+        .outcomes_by_category
+        .print_percentages(&category_names, &OUTCOMES, &OUTCOMES);
+
+    println!();
+
+    println!("{} category given our hand category:\n", opponent_hand);
+
+    // Only show the opponent categories that came up, to keep the table
+    // narrow.
+    let (columns, column_labels): (Vec<&str>, Vec<&str>) = Category::kinds()
+        .iter()
+        .filter(|category| {
+            results
+                .opponent_hand_categories
+                .contains_key(category.name())
+        })
+        .map(|category| (category.name(), CATEGORY_ABBREVIATIONS[category.rank()]))
+        .unzip();
+    results.opponent_categories_by_category.print_percentages(
+        &category_names,
+        &columns,
+        &column_labels,
     );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
 
-    game_outcomes.print_percentages();
+    use super::super::card::Card;
+    use super::super::random::Xorshift;
+    use super::{bar, simulate_in_parallel, simulate_in_parallel_with, Deal, BAR_WIDTH, TIE};
+
+    #[test]
+    fn test_bar_is_proportional() {
+        assert_eq!(bar(0.0), "");
+        assert_eq!(bar(0.001), ".");
+        assert_eq!(bar(0.5).len(), BAR_WIDTH / 2);
+        assert_eq!(bar(1.0).len(), BAR_WIDTH);
+    }
+
+    #[test]
+    fn test_simulate_in_parallel_is_deterministic() {
+        let hole_cards = Card::try_vec_from("10s js").unwrap();
+        let community_cards = Card::try_vec_from("qs 9s 3d").unwrap();
+        let run = |num_threads| {
+            let results = simulate_in_parallel::<Xorshift>(
+                &hole_cards,
+                &community_cards,
+                1001,
+                7,
+                num_threads,
+            );
+            assert_eq!(results.num_iterations, 1001);
+            assert_eq!(results.game_outcomes.values().sum::<usize>(), 1001);
+            let mut outcomes = results
+                .game_outcomes
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>();
+            outcomes.sort();
+            outcomes
+        };
+
+        assert_eq!(run(1), run(1));
+        assert_eq!(run(3), run(3));
+    }
+
+    #[test]
+    fn test_cross_tabs_agree_with_counters() {
+        let hole_cards = Card::try_vec_from("10s js").unwrap();
+        let community_cards = Card::try_vec_from("qs 9s 3d").unwrap();
+        let results = simulate_in_parallel_with::<Xorshift, _, _>(
+            &hole_cards,
+            &community_cards,
+            1,
+            1000,
+            7,
+            2,
+            &|| |_: &Deal| ControlFlow::Continue(()),
+        );
+
+        for (category, count) in results.hand_categories.iter() {
+            let outcomes = &results.outcomes_by_category[category];
+            let opponent_categories = &results.opponent_categories_by_category[category];
+            assert_eq!(outcomes.values().sum::<usize>(), *count);
+            assert_eq!(opponent_categories.values().sum::<usize>(), *count);
+        }
+        for (outcome, count) in results.game_outcomes.iter() {
+            let total = results
+                .outcomes_by_category
+                .values()
+                .map(|outcomes| outcomes.get(outcome).copied().unwrap_or(0))
+                .sum::<usize>();
+            assert_eq!(total, *count);
+        }
+    }
+
+    #[test]
+    fn test_split_pots_share_the_pot() {
+        // Everyone plays the royal flush on the board, so every pot is split
+        // between us and all three opponents.
+        let hole_cards = Card::try_vec_from("2c 3d").unwrap();
+        let community_cards = Card::try_vec_from("as ks qs js 10s").unwrap();
+        let results = simulate_in_parallel_with::<Xorshift, _, _>(
+            &hole_cards,
+            &community_cards,
+            3,
+            100,
+            7,
+            1,
+            &|| {
+                |deal: &Deal| {
+                    assert_eq!(deal.opponent_hole_cards.len(), 6);
+                    assert_eq!(deal.pot_share, 0.25);
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+
+        assert_eq!(results.game_outcomes[TIE], 100);
+        assert_eq!(results.equity().mean, 0.25);
+    }
 }
@@ -0,0 +1,555 @@
+// https://en.wikipedia.org/wiki/Variance_reduction
+use std::cmp::Ordering;
+
+use super::card::Card;
+use super::hand::Hand;
+use super::random::Rng;
+use super::texas::{NUM_COMMUNITY_CARDS, NUM_HOLE_CARDS};
+
+/// A Monte Carlo estimate of a mean along with its uncertainty.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Estimate {
+    pub mean: f64,
+    /// The variance of `mean` itself, i.e. the squared standard error.
+    pub variance: f64,
+    pub samples: usize,
+}
+
+impl Estimate {
+    pub fn std_error(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Returns the bounds of the approximate 95% confidence interval.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let margin = 1.96 * self.std_error();
+        (self.mean - margin, self.mean + margin)
+    }
+}
+
+/// Returns the bounds of the 95% Wilson score interval for a proportion,
+/// which unlike the usual normal approximation behaves well for proportions
+/// close to 0 or 1. See
+/// https://en.wikipedia.org/wiki/Binomial_proportion_confidence_interval#Wilson_score_interval.
+pub fn wilson_interval(successes: usize, trials: usize) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let z = 1.96;
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+/// Accumulates samples to compute their mean and the variance of that mean.
+#[derive(Default)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.sum_of_squares += value * value;
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    /// The unbiased sample variance of the individual samples.
+    fn sample_variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        let n = self.count as f64;
+        ((self.sum_of_squares - self.sum * self.sum / n) / (n - 1.0)).max(0.0)
+    }
+
+    fn estimate(&self) -> Estimate {
+        Estimate {
+            mean: self.mean(),
+            variance: self.sample_variance() / self.count as f64,
+            samples: self.count,
+        }
+    }
+}
+
+/// Returns our share of the pot (1, 0.5 or 0) after a showdown against one
+/// opponent.
+fn showdown_equity(hole_cards: &[Card], opponent_hole_cards: &[Card], board: &[Card]) -> f64 {
+    let hand = Hand::from([board, hole_cards].concat());
+    let opponent_hand = Hand::from([board, opponent_hole_cards].concat());
+    match hand.cmp(&opponent_hand) {
+        Ordering::Greater => 1.0,
+        Ordering::Equal => 0.5,
+        Ordering::Less => 0.0,
+    }
+}
+
+fn remaining_deck(used: &[&[Card]]) -> Vec<Card> {
+    let used = used.concat();
+    let mut deck = Card::new_deck();
+    deck.retain(|card| !used.contains(card));
+    deck
+}
+
+/// Deals the opponent's hole cards and the rest of the board from the deck,
+/// after any cards that have already been added to the board.
+fn play_out<R: Rng>(hole_cards: &[Card], board: &[Card], deck: &mut [Card], random: &mut R) -> f64 {
+    let num_cards_to_draw = NUM_COMMUNITY_CARDS - board.len();
+    let dealt = random.deal(deck, NUM_HOLE_CARDS + num_cards_to_draw);
+    let (opponent_hole_cards, drawn_cards) = dealt.split_at(NUM_HOLE_CARDS);
+    let board = [board, drawn_cards].concat();
+    showdown_equity(hole_cards, opponent_hole_cards, &board)
+}
+
+/// Estimates our equity against one random opponent by plain Monte Carlo
+/// sampling.
+pub fn plain_equity<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_samples: usize,
+    random: &mut R,
+) -> Estimate {
+    let mut deck = remaining_deck(&[hole_cards, board]);
+    let mut acc = Accumulator::default();
+    for _ in 0..num_samples {
+        acc.add(play_out(hole_cards, board, &mut deck, random));
+    }
+    acc.estimate()
+}
+
+/// Estimates our equity by stratifying over the next board card: every
+/// unseen card gets an equal share of the samples, which removes the
+/// variance caused by some cards being over- or under-represented.
+///
+/// Returns `None` if the board is already complete.
+pub fn stratified_equity<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_samples: usize,
+    random: &mut R,
+) -> Option<Estimate> {
+    if board.len() >= NUM_COMMUNITY_CARDS {
+        return None;
+    }
+
+    let deck = remaining_deck(&[hole_cards, board]);
+    let num_strata = deck.len() as f64;
+    // We need at least two samples per stratum to estimate its variance.
+    let samples_per_stratum = (num_samples / deck.len()).max(2);
+    let mut mean = 0.0;
+    let mut variance = 0.0;
+
+    for next_card in deck.iter() {
+        let mut stratum_deck = deck.clone();
+        stratum_deck.retain(|card| card != next_card);
+        let stratum_board = [board, &[*next_card]].concat();
+        let mut acc = Accumulator::default();
+        for _ in 0..samples_per_stratum {
+            acc.add(play_out(
+                hole_cards,
+                &stratum_board,
+                &mut stratum_deck,
+                random,
+            ));
+        }
+        let estimate = acc.estimate();
+        mean += estimate.mean / num_strata;
+        variance += estimate.variance / (num_strata * num_strata);
+    }
+
+    Some(Estimate {
+        mean,
+        variance,
+        samples: samples_per_stratum * deck.len(),
+    })
+}
+
+/// The games played after one particular next board card.
+struct Stratum {
+    board: Vec<Card>,
+    deck: Vec<Card>,
+    acc: Accumulator,
+}
+
+impl Stratum {
+    fn new(board: &[Card], deck: &[Card], next_card: Card) -> Self {
+        Stratum {
+            board: [board, &[next_card]].concat(),
+            deck: deck
+                .iter()
+                .copied()
+                .filter(|&card| card != next_card)
+                .collect(),
+            acc: Accumulator::default(),
+        }
+    }
+
+    fn play<R: Rng>(&mut self, hole_cards: &[Card], num_games: usize, random: &mut R) {
+        for _ in 0..num_games {
+            self.acc
+                .add(play_out(hole_cards, &self.board, &mut self.deck, random));
+        }
+    }
+}
+
+/// The share of optimal allocation's samples spent on a pilot run, which
+/// plays the same number of games after every possible next board card to
+/// measure how much each one's outcome varies.
+const PILOT_SHARE: f64 = 0.2;
+
+/// The share of the remaining samples spread evenly over every next board
+/// card, in case the pilot run underestimated how much a card's outcome
+/// varies.
+const UNIFORM_SHARE: f64 = 0.1;
+
+/// Estimates our equity by stratifying over the next board card like
+/// `stratified_equity`, but with the optimal (Neyman) allocation: each card
+/// gets a share of the samples in proportion to how much our pot share
+/// varies after it, as measured by a pilot run. Every card is still equally
+/// likely to be dealt, so each one's equity counts equally. Samples go to
+/// the runouts that are still up for grabs, like the cards that make a draw
+/// or counterfeit our hand, rather than to cards after which the pot is
+/// already decided. See https://en.wikipedia.org/wiki/Neyman_allocation.
+///
+/// Returns `None` if the board is already complete.
+pub fn optimally_allocated_equity<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    num_samples: usize,
+    random: &mut R,
+) -> Option<Estimate> {
+    if board.len() >= NUM_COMMUNITY_CARDS {
+        return None;
+    }
+
+    let deck = remaining_deck(&[hole_cards, board]);
+    let num_cards = deck.len() as f64;
+    let mut strata = deck
+        .iter()
+        .map(|next_card| Stratum::new(board, &deck, *next_card))
+        .collect::<Vec<_>>();
+
+    // We need at least two samples per card to estimate its variance.
+    let pilot_games_per_card = ((num_samples as f64 * PILOT_SHARE / num_cards) as usize).max(2);
+    for stratum in strata.iter_mut() {
+        stratum.play(hole_cards, pilot_games_per_card, random);
+    }
+
+    let num_remaining = num_samples.saturating_sub(pilot_games_per_card * strata.len()) as f64;
+    let deviations = strata
+        .iter()
+        .map(|stratum| stratum.acc.sample_variance().sqrt())
+        .collect::<Vec<_>>();
+    let total_deviation = deviations.iter().sum::<f64>();
+    for (stratum, deviation) in strata.iter_mut().zip(deviations.iter()) {
+        let share = if total_deviation > 0.0 {
+            (1.0 - UNIFORM_SHARE) * deviation / total_deviation + UNIFORM_SHARE / num_cards
+        } else {
+            1.0 / num_cards
+        };
+        stratum.play(hole_cards, (num_remaining * share).round() as usize, random);
+    }
+
+    let (mut mean, mut variance, mut samples) = (0.0, 0.0, 0);
+    for stratum in strata.iter() {
+        let estimate = stratum.acc.estimate();
+        mean += estimate.mean / num_cards;
+        variance += estimate.variance / (num_cards * num_cards);
+        samples += estimate.samples;
+    }
+    Some(Estimate {
+        mean,
+        variance,
+        samples,
+    })
+}
+
+/// The estimated difference in equity between two hands of ours.
+pub struct Comparison {
+    pub other_hole_cards: Vec<Card>,
+    pub difference: Estimate,
+    /// The variance the difference would have had if each hand had been
+    /// simulated on its own independent runouts.
+    pub independent_variance: f64,
+}
+
+impl Comparison {
+    /// Returns how large the difference's variance is, relative to
+    /// simulating each hand independently, or `None` if neither hand's
+    /// result varies.
+    pub fn relative_variance(&self) -> Option<f64> {
+        if self.independent_variance > 0.0 {
+            Some(self.difference.variance / self.independent_variance)
+        } else {
+            None
+        }
+    }
+}
+
+/// Estimates how much more equity `hole_cards` has than `other_hole_cards`
+/// by playing both against the same opponent hands and runouts. Because the
+/// two hands' results are strongly correlated, their difference has much
+/// less variance than if each were simulated separately.
+///
+/// Note that the opponent can never hold any of the cards from either hand.
+pub fn compare_on_common_runouts<R: Rng>(
+    hole_cards: &[Card],
+    other_hole_cards: &[Card],
+    board: &[Card],
+    num_samples: usize,
+    random: &mut R,
+) -> Comparison {
+    let mut deck = remaining_deck(&[hole_cards, other_hole_cards, board]);
+    let num_cards_to_draw = NUM_COMMUNITY_CARDS - board.len();
+    let mut ours = Accumulator::default();
+    let mut theirs = Accumulator::default();
+    let mut difference = Accumulator::default();
+
+    for _ in 0..num_samples {
+        let dealt = random.deal(&mut deck, NUM_HOLE_CARDS + num_cards_to_draw);
+        let (opponent_hole_cards, drawn_cards) = dealt.split_at(NUM_HOLE_CARDS);
+        let full_board = [board, drawn_cards].concat();
+        let equity = showdown_equity(hole_cards, opponent_hole_cards, &full_board);
+        let other_equity = showdown_equity(other_hole_cards, opponent_hole_cards, &full_board);
+        ours.add(equity);
+        theirs.add(other_equity);
+        difference.add(equity - other_equity);
+    }
+
+    Comparison {
+        other_hole_cards: other_hole_cards.to_vec(),
+        difference: difference.estimate(),
+        independent_variance: (ours.sample_variance() + theirs.sample_variance())
+            / num_samples as f64,
+    }
+}
+
+/// Our equity as estimated by each sampling method.
+pub struct VarianceReport {
+    pub hole_cards: Vec<Card>,
+    pub plain: Estimate,
+    /// This is `None` if the board is already complete.
+    pub stratified: Option<Estimate>,
+    /// This is `None` if the board is already complete.
+    pub optimally_allocated: Option<Estimate>,
+    pub comparison: Option<Comparison>,
+}
+
+/// Estimates our equity with each sampling method, and optionally compares
+/// it to the equity of other hole cards on common runouts.
+pub fn variance_report<R: Rng>(
+    hole_cards: &[Card],
+    board: &[Card],
+    compare_to: Option<&[Card]>,
+    num_samples: usize,
+    random: &mut R,
+) -> VarianceReport {
+    variance_report_with(hole_cards, board, compare_to, num_samples, random, || false)
+        .expect("the report is never stopped")
+}
+
+/// Like `variance_report`, but checks `is_stopped` before each estimate,
+/// and gives up with `None` once it returns true.
+pub fn variance_report_with<R: Rng, S: Fn() -> bool>(
+    hole_cards: &[Card],
+    board: &[Card],
+    compare_to: Option<&[Card]>,
+    num_samples: usize,
+    random: &mut R,
+    is_stopped: S,
+) -> Option<VarianceReport> {
+    if is_stopped() {
+        return None;
+    }
+    let plain = plain_equity(hole_cards, board, num_samples, random);
+    if is_stopped() {
+        return None;
+    }
+    let stratified = stratified_equity(hole_cards, board, num_samples, random);
+    if is_stopped() {
+        return None;
+    }
+    let optimally_allocated = optimally_allocated_equity(hole_cards, board, num_samples, random);
+    let comparison = match compare_to {
+        Some(_) if is_stopped() => return None,
+        Some(other_hole_cards) => Some(compare_on_common_runouts(
+            hole_cards,
+            other_hole_cards,
+            board,
+            num_samples,
+            random,
+        )),
+        None => None,
+    };
+
+    Some(VarianceReport {
+        hole_cards: hole_cards.to_vec(),
+        plain,
+        stratified,
+        optimally_allocated,
+        comparison,
+    })
+}
+
+impl VarianceReport {
+    /// Returns how large the estimate's variance is, relative to plain
+    /// sampling, or `None` if plain sampling has no variance, e.g. because
+    /// the outcome is already decided.
+    pub fn relative_variance(&self, estimate: &Estimate) -> Option<f64> {
+        if self.plain.variance > 0.0 {
+            Some(estimate.variance / self.plain.variance)
+        } else {
+            None
+        }
+    }
+
+    fn print_estimate(&self, label: &str, estimate: &Option<Estimate>) {
+        match estimate {
+            Some(estimate) => println!(
+                "  {:20} {:.2}% ± {:.2}%  ({})",
+                label,
+                estimate.mean * 100.0,
+                estimate.std_error() * 100.0,
+                describe_relative_variance(self.relative_variance(estimate), "plain sampling")
+            ),
+            None => println!("  {:20} n/a (the board is complete)", label),
+        }
+    }
+
+    /// Prints each estimate along with how much it reduces the variance
+    /// compared to plain sampling.
+    pub fn print(&self) {
+        println!(
+            "Equity estimates using roughly {} samples with each sampling method:\n",
+            self.plain.samples
+        );
+        println!(
+            "  {:20} {:.2}% ± {:.2}%",
+            "Plain",
+            self.plain.mean * 100.0,
+            self.plain.std_error() * 100.0
+        );
+        self.print_estimate("Stratified", &self.stratified);
+        self.print_estimate("Optimal allocation", &self.optimally_allocated);
+
+        if let Some(comparison) = &self.comparison {
+            println!();
+            println!(
+                "Equity of {} minus that of {} on {} common runouts:\n",
+                Card::vec_to_string(&self.hole_cards),
+                Card::vec_to_string(&comparison.other_hole_cards),
+                comparison.difference.samples
+            );
+            println!(
+                "  {:+.2}% ± {:.2}%  ({})",
+                comparison.difference.mean * 100.0,
+                comparison.difference.std_error() * 100.0,
+                describe_relative_variance(
+                    comparison.relative_variance(),
+                    "simulating each hand independently"
+                )
+            );
+        }
+    }
+}
+
+fn describe_relative_variance(relative_variance: Option<f64>, baseline: &str) -> String {
+    match relative_variance {
+        Some(relative_variance) => {
+            format!("variance {:.1}% of {}", relative_variance * 100.0, baseline)
+        }
+        None => format!("{} has no variance to reduce", baseline),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::card::Card;
+    use super::super::random::{FromSeed, Xorshift};
+    use super::{
+        compare_on_common_runouts, optimally_allocated_equity, plain_equity, stratified_equity,
+        wilson_interval, Estimate, VarianceReport,
+    };
+
+    fn cards(value: &'static str) -> Vec<Card> {
+        Card::try_vec_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_methods_agree() {
+        let hole_cards = cards("10s js");
+        let board = cards("qs 9s 3d");
+        let mut random = Xorshift::from_seed(1);
+        let plain = plain_equity(&hole_cards, &board, 20000, &mut random);
+        let stratified = stratified_equity(&hole_cards, &board, 20000, &mut random).unwrap();
+        let allocated =
+            optimally_allocated_equity(&hole_cards, &board, 20000, &mut random).unwrap();
+
+        // Spending more samples on the cards that matter beats plain
+        // sampling.
+        assert!(allocated.variance < plain.variance);
+        for estimate in [stratified, allocated].iter() {
+            let margin = 4.0 * (plain.variance + estimate.variance).sqrt();
+            assert!(
+                (plain.mean - estimate.mean).abs() < margin,
+                "{:?} vs {:?}",
+                plain,
+                estimate
+            );
+        }
+    }
+
+    #[test]
+    fn test_wilson_interval_works() {
+        let (low, high) = wilson_interval(50, 100);
+        assert!(low < 0.5 && high > 0.5);
+        assert!((high - 0.5 - (0.5 - low)).abs() < 1e-9);
+
+        let (low, high) = wilson_interval(0, 100);
+        assert_eq!(low, 0.0);
+        assert!(high > 0.0 && high < 0.05);
+    }
+
+    #[test]
+    fn test_complete_boards_cannot_be_stratified() {
+        let mut random = Xorshift::from_seed(1);
+        let board = cards("qs 9s 3d 2c 2h");
+        assert!(stratified_equity(&cards("as ah"), &board, 10, &mut random).is_none());
+        assert!(optimally_allocated_equity(&cards("as ah"), &board, 10, &mut random).is_none());
+    }
+
+    #[test]
+    fn test_decided_outcomes_have_no_relative_variance() {
+        let decided = Estimate {
+            mean: 1.0,
+            variance: 0.0,
+            samples: 100,
+        };
+        let report = VarianceReport {
+            hole_cards: cards("as ah"),
+            plain: decided,
+            stratified: Some(decided),
+            optimally_allocated: None,
+            comparison: None,
+        };
+        assert_eq!(report.relative_variance(&decided), None);
+    }
+
+    #[test]
+    fn test_common_runouts_reduce_variance() {
+        let mut random = Xorshift::from_seed(1);
+        let comparison =
+            compare_on_common_runouts(&cards("ah kd"), &cards("as qd"), &[], 5000, &mut random);
+        assert!(comparison.difference.mean > 0.0);
+        assert!(comparison.difference.variance < comparison.independent_variance / 2.0);
+    }
+}